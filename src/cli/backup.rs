@@ -0,0 +1,80 @@
+use std::path::Path;
+
+use clap::Subcommand;
+
+use crate::{components::tasks::Tasks, storage};
+
+#[derive(Debug, Subcommand)]
+pub(crate) enum Backup {
+    /// Lists the available backups, most recent first.
+    List,
+
+    /// Replaces the task list with a backup.
+    Restore {
+        /// Backup number, as shown by `backup list`.
+        num: usize,
+    },
+}
+
+impl Backup {
+    pub(crate) fn is_read_only(&self) -> bool {
+        matches!(self, Backup::List)
+    }
+
+    pub(crate) fn run(self, filepath: &Path, tasks: &mut Tasks) -> anyhow::Result<()> {
+        let backups = storage::list_backups(filepath)?;
+        match self {
+            Backup::List => {
+                for (num, backup) in backups.iter().enumerate() {
+                    let timestamp = storage::backup_timestamp(backup)
+                        .map(|timestamp| timestamp.format("%Y-%m-%d %H:%M:%S").to_string())
+                        .unwrap_or_else(|| "UNKNOWN DATE".to_string());
+                    let num_tasks = Tasks::load(backup)?.num_tasks();
+                    println!("{:>3}  {timestamp}  {num_tasks} tasks", num + 1);
+                }
+            }
+            Backup::Restore { num } => {
+                let backup = num
+                    .checked_sub(1)
+                    .and_then(|index| backups.get(index))
+                    .ok_or_else(|| anyhow::anyhow!("no backup number {num}"))?;
+                *tasks = Tasks::load(backup)?;
+                println!("Restored backup {num}.");
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{cli::save, components::task::Task, journal::Journal, storage::{sibling_filepath, BACKUP_COUNT}};
+
+    use super::*;
+
+    #[test]
+    fn save_rotates_and_restores_backups() {
+        let directory = std::env::temp_dir().join(format!("wwida-backup-{}", std::process::id()));
+        std::fs::create_dir_all(&directory).expect("could not create test directory");
+        let filepath = directory.join("cache");
+
+        let mut tasks = Tasks::new();
+        for _ in 0..BACKUP_COUNT + 3 {
+            tasks.add_task(Task::from_strings("back me up", None, None, None, None, vec![]).expect("could not create task"));
+            save(&filepath, tasks.clone(), &Journal::default()).expect("could not save");
+            // backups are named after the time they are taken, to the millisecond
+            std::thread::sleep(std::time::Duration::from_millis(2));
+        }
+        assert!(!sibling_filepath(&filepath, ".tmp").exists());
+        assert_eq!(storage::list_backups(&filepath).expect("could not list backups").len(), BACKUP_COUNT);
+
+        // the latest backup holds the list as it was before the last save
+        let mut restored = Tasks::load(&filepath).expect("could not load");
+        assert_eq!(restored.num_tasks(), BACKUP_COUNT + 3);
+        Backup::Restore { num: 1 }.run(&filepath, &mut restored).expect("could not restore");
+        assert_eq!(restored.num_tasks(), BACKUP_COUNT + 2);
+        assert!(Backup::Restore { num: BACKUP_COUNT + 1 }.run(&filepath, &mut restored).is_err());
+
+        std::fs::remove_dir_all(directory).expect("could not remove test directory");
+    }
+}
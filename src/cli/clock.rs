@@ -0,0 +1,107 @@
+use clap::Subcommand;
+
+use crate::components::{
+    tasks::Tasks,
+    time::{duration::TimeInterval, span::Span, to_local},
+    TaskId,
+};
+
+#[derive(Debug, Subcommand)]
+pub(crate) enum Clock {
+    /// Starts recording time spent on a task, clocking out of any other task first.
+    In { id: TaskId },
+
+    /// Stops recording time spent on the task clocked in.
+    Out,
+
+    /// Shows which task is clocked in.
+    Status,
+
+    /// Prints the recorded time as CSV, e.g. for timesheets.
+    Export {
+        /// Only include time started within an interval, e.g. this-week, last-month or 1-sep..15-sep.
+        interval: Option<TimeInterval>,
+    },
+}
+
+impl Clock {
+    pub(crate) fn is_read_only(&self) -> bool {
+        matches!(self, Clock::Status | Clock::Export { .. })
+    }
+
+    pub(crate) fn run(self, tasks: &mut Tasks) -> anyhow::Result<()> {
+        match self {
+            Clock::In { id } => {
+                if tasks.get_task_mut_err(id)?.is_clocked_in() {
+                    return Err(anyhow::anyhow!("already clocked in on task {id}"));
+                }
+                clock_out(tasks)?;
+                tasks.get_task_mut_err(id)?.clock_in()?;
+                println!("Clocked in on task {id}.");
+            }
+            Clock::Out => {
+                if clock_out(tasks)?.is_none() {
+                    return Err(anyhow::anyhow!("not clocked in on any task"));
+                }
+            }
+            Clock::Status => match tasks.clocked_in().and_then(|id| tasks.get_task(id)) {
+                Some(task) => {
+                    let running = task.time_log.last().expect("clocked in tasks have a time entry").duration();
+                    println!(
+                        "Clocked in on task {} \"{}\" for {}, {} in total.",
+                        task.id,
+                        task.short,
+                        Span::whole_minutes(running),
+                        Span::whole_minutes(task.time_spent())
+                    );
+                }
+                None => println!("Not clocked in on any task."),
+            },
+            Clock::Export { interval } => {
+                println!("id,task,date,start,end,minutes");
+                let mut rows = tasks
+                    .iter()
+                    .chain(tasks.iter_archived())
+                    .flat_map(|task| task.time_log.iter().map(move |entry| (task, entry)))
+                    .filter(|(_, entry)| interval.is_none_or(|interval| interval.contains(&to_local(entry.start).date())))
+                    .collect::<Vec<_>>();
+                rows.sort_by_key(|(task, entry)| (entry.start, task.id));
+                for (task, entry) in rows {
+                    let start = to_local(entry.start);
+                    let end = entry.end.map(|end| to_local(end).format("%H:%M").to_string()).unwrap_or_default();
+                    println!(
+                        "{},{},{},{},{end},{}",
+                        task.id,
+                        csv_field(&task.short.to_string()),
+                        start.format("%Y-%m-%d"),
+                        start.format("%H:%M"),
+                        entry.duration().num_minutes()
+                    );
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Clocks out of the task clocked in, if any, returning its ID.
+fn clock_out(tasks: &mut Tasks) -> anyhow::Result<Option<TaskId>> {
+    let id = match tasks.clocked_in() {
+        Some(id) => id,
+        None => return Ok(None),
+    };
+    let task = tasks.get_task_mut_err(id)?;
+    let duration = task.clock_out().expect("task is clocked in");
+    println!("Clocked out of task {id} after {}, {} in total.", Span::whole_minutes(duration), Span::whole_minutes(task.time_spent()));
+    Ok(Some(id))
+}
+
+/// Quotes a CSV field if it contains a separator, quote or line break.
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
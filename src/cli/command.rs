@@ -1,42 +1,71 @@
-use std::collections::BTreeSet;
+use std::{collections::{BTreeSet, HashMap}, path::{Path, PathBuf}};
 
+use anyhow::Context as _;
 use clap::Subcommand;
 use itertools::Itertools;
 
 use crate::{
     components::{
-        category::Category, short_string::ShortString, task::Task, tasks::Tasks, time::date_specifier::DateSpecifier,
+        category::Category, query::Query, short_string::ShortString, task::Task, tasks::Tasks, time::{date_specifier::DateSpecifier, moment::Moment, recurrence::Recurrence, span::Span, today},
         TaskId, priority::Priority, tag::{tags_from_comma_separated_string, Tag},
     },
-    format::TaskListFormatter,
+    config::config,
+    format::{print_header, GroupBy, Row, TaskListFormatter},
+    journal::Journal,
+    storage::{
+        lists::{project_list_filepath, DEFAULT_LIST},
+        CacheLock, StorageFormat,
+    },
 };
 
-use super::{summary::Summary, update::Update, sort::SortBy};
+use super::{backup::Backup, clock::Clock, command_line, config::Config, doctor, graph::GraphFormat, list::List, recur::Recur, remind, report::ReportCommand, save, summary::Summary, update::Update, sort::SortBy, storage::Storage, checked_deadline, Context};
 
 #[derive(Debug, Subcommand)]
 pub(crate) enum Command {
     /// Add a new task to the to-do list.
     Add {
-        /// Short description of the task, must be <= 50 chars long.
+        /// Short description of the task, must be <= 50 chars long unless configured otherwise.
         short: ShortString,
 
         /// Optional long description of the task.
         #[clap(short, long)]
         long: Option<String>,
 
-        #[clap(value_enum, short, long, default_value_t)]
-        category: Category,
+        /// Task category, defaults to the configured one.
+        #[clap(value_enum, short, long)]
+        category: Option<Category>,
 
-        /// Task deadline, e.g. today, tomorrow, this/tuesday, next/friday or 01/08/2022.
+        /// Task deadline, e.g. today, tomorrow@14:30, "friday 9am", next/friday, 01/08/2022 or 2022-08-01T09:00.
         #[clap(short, long)]
         deadline: Option<DateSpecifier>,
 
-        #[clap(short, long, value_enum, default_value_t)]
-        priority: Priority,
+        /// Task priority, defaults to the configured one.
+        #[clap(short, long, value_enum)]
+        priority: Option<Priority>,
 
         /// Comma-separated list of tags.
         #[clap(short, long, default_value_t)]
         tags: String,
+
+        /// ID of the task this one is a step of.
+        #[clap(long)]
+        parent: Option<TaskId>,
+
+        /// Comma-separated IDs of tasks which have to be completed first.
+        #[clap(long, value_delimiter = ',')]
+        depends_on: Vec<TaskId>,
+
+        /// Adds the next occurrence when the task is completed: daily, weekly/monday,thursday, monthly/n or every/n.
+        #[clap(long)]
+        recur: Option<Recurrence>,
+
+        /// Comma-separated times before the deadline to be reminded at, e.g. 1d,2h; the deadline itself always is.
+        #[clap(long, value_delimiter = ',')]
+        remind: Vec<Span>,
+
+        /// Hides the task from pending tasks until the given date, e.g. next/monday or +2w.
+        #[clap(short, long)]
+        wait: Option<DateSpecifier>,
     },
 
     /// Set an unstarted task to in progress.
@@ -44,6 +73,29 @@ pub(crate) enum Command {
         id: TaskId,
     },
 
+    /// Hides a pending task until the given date, when it shows up again by itself.
+    Snooze {
+        id: TaskId,
+
+        /// Date to wait until, e.g. next/monday, +3d or "tomorrow 9am".
+        #[clap(required = true)]
+        until: Vec<String>,
+    },
+
+    /// Records time spent on tasks.
+    Clock {
+        #[clap(subcommand)]
+        clock: Clock,
+    },
+
+    /// Records time spent on a task without clocking in, e.g. `wwida log-time 3 1h30m`.
+    LogTime {
+        id: TaskId,
+
+        /// Time spent, e.g. 45m, 1h30m or 1d.
+        span: Span,
+    },
+
     /// Update a task.
     Update {
         id: TaskId,
@@ -52,31 +104,219 @@ pub(crate) enum Command {
         update: Update,
     },
 
+    /// Applies the same update to all active tasks matching a query, e.g. `wwida bulk "tag:release" priority high`.
+    Bulk {
+        /// Query selecting the tasks to update, see `wwida print --query`.
+        query: Query,
+
+        /// Only list the tasks which would be updated.
+        #[clap(long)]
+        dry_run: bool,
+
+        #[clap(subcommand)]
+        update: Update,
+    },
+
     /// Print misc. summaries.
     Print {
-        #[clap(long, value_enum, default_value_t)]
-        format: TaskListFormatter,
+        /// Output format, defaults to the configured one.
+        #[clap(long, value_enum, global = true)]
+        format: Option<TaskListFormatter>,
 
-        /// Comma-separated list of tags.
-        #[clap(long)]
+        /// Comma-separated list of tags, defaults to the configured filter.
+        #[clap(long, global = true)]
         filter: Option<String>,
 
+        /// Only show tasks matching a query, e.g. "tag:work and not status:blocked" or "priority>=high due<friday".
+        #[clap(short, long, global = true)]
+        query: Option<Query>,
+
         #[clap(subcommand)]
         summary: Summary,
 
-        #[clap(short, long, value_enum, default_value_t)]
-        sort: SortBy,
+        /// Sorting rule, e.g. priority,-deadline,id, defaults to the configured one.
+        #[clap(short, long, global = true, allow_hyphen_values = true)]
+        sort: Option<SortBy>,
+
+        /// Show tasks in groups, each under a header with its number of tasks.
+        #[clap(long, value_enum, global = true)]
+        group_by: Option<GroupBy>,
+
+        /// Include archived tasks.
+        #[clap(short, long, global = true)]
+        archived: bool,
+
+        /// Show tasks of all lists, one list after another.
+        #[clap(long, global = true)]
+        all_lists: bool,
+
+        /// Also show the global default list after the project list.
+        #[clap(long, global = true)]
+        with_global: bool,
     },
 
-    /// Deletes all tasks.
+    /// Shows the most urgent task which can be worked on, i.e. neither blocked nor snoozed.
+    Next {
+        /// Only consider tasks matching a query, e.g. "tag:work".
+        #[clap(short, long)]
+        query: Option<Query>,
+    },
+
+    /// Runs a saved report, or defines, lists and removes reports.
+    Report {
+        #[clap(subcommand)]
+        report: ReportCommand,
+    },
+
+    /// Shows the tasks a task depends on, and the tasks depending on it.
+    Deps {
+        id: TaskId,
+    },
+
+    /// Prints the dependencies between tasks as a Graphviz or Mermaid graph.
+    Graph {
+        /// Graph language to emit.
+        #[clap(long, value_enum, default_value = "dot")]
+        format: GraphFormat,
+
+        /// Comma-separated list of tags, defaults to the configured filter.
+        #[clap(long)]
+        filter: Option<String>,
+
+        /// Include archived tasks.
+        #[clap(short, long)]
+        archived: bool,
+    },
+
+    /// Creates a project list in the current directory, used by wwida anywhere below it.
+    Init {
+        #[clap(long, value_enum, default_value = "bson")]
+        format: StorageFormat,
+    },
+
+    /// Deletes all active tasks, leaving the archive untouched.
     Clear,
 
+    /// Permanently deletes one or more active or archived tasks.
+    Delete {
+        #[clap(required = true)]
+        ids: Vec<TaskId>,
+    },
+
+    /// Moves one or more tasks out of the active list and into the archive.
+    Archive {
+        #[clap(required = true)]
+        ids: Vec<TaskId>,
+    },
+
+    /// Moves an archived task back into the active list.
+    Restore {
+        id: TaskId,
+    },
+
+    /// Moves one or more tasks into another list, where they get new IDs.
+    Move {
+        #[clap(required = true)]
+        ids: Vec<TaskId>,
+
+        /// Name of the destination list.
+        #[clap(long)]
+        to: String,
+    },
+
+    /// Shows all task lists.
+    Lists,
+
+    /// Creates, renames or deletes task lists.
+    List {
+        #[clap(subcommand)]
+        list: List,
+    },
+
+    /// Lists recurring tasks, or changes their recurrence.
+    Recur {
+        #[clap(subcommand)]
+        recur: Recur,
+    },
+
+    /// Lists upcoming reminders, or sends those which are due.
+    Remind {
+        /// Send the due reminders through the configured notify command instead of listing them.
+        #[clap(long)]
+        due: bool,
+    },
+
+    /// Shows the date a date expression resolves to, e.g. `wwida date end-of-next-month`.
+    Date {
+        #[clap(required = true)]
+        expression: Vec<String>,
+    },
+
     /// Shows all currently used tags.
     Tags,
+
+    /// Reverts the last command that changed the task list.
+    Undo,
+
+    /// Re-applies the last undone command.
+    Redo,
+
+    /// Lists the last commands that changed the task list.
+    History {
+        /// Number of commands to show.
+        #[clap(short, long, default_value_t = 10)]
+        num: usize,
+    },
+
+    /// Inspects or converts the storage of the task list.
+    Storage {
+        #[clap(subcommand)]
+        storage: Storage,
+    },
+
+    /// Lists or restores automatic backups of the task list.
+    Backup {
+        #[clap(subcommand)]
+        backup: Backup,
+    },
 }
 
 impl Command {
-    pub(crate) fn run(self, tasks: &mut Tasks) -> anyhow::Result<()> {
+    /// Whether the command leaves the task list and journal untouched, in which case nothing is saved.
+    pub(crate) fn is_read_only(&self) -> bool {
+        match self {
+            Command::Print { .. }
+            | Command::Next { .. }
+            | Command::Report { .. }
+            | Command::Bulk { dry_run: true, .. }
+            | Command::Tags
+            | Command::Deps { .. }
+            | Command::Graph { .. }
+            | Command::Date { .. }
+            | Command::History { .. }
+            | Command::Storage { .. }
+            | Command::Lists
+            | Command::List { .. }
+            | Command::Init { .. } => true,
+            Command::Backup { backup } => backup.is_read_only(),
+            Command::Recur { recur } => recur.is_read_only(),
+            Command::Clock { clock } => clock.is_read_only(),
+            Command::Remind { due } => !due,
+            _ => false,
+        }
+    }
+
+    /// Whether the command changes the task list and should therefore be recorded in the journal.
+    pub(crate) fn is_journaled(&self) -> bool {
+        !self.is_read_only() && !self.saves_itself() && !matches!(self, Command::Undo | Command::Redo | Command::Remind { .. })
+    }
+
+    /// Whether the command journals and saves the lists it changes by itself, as it changes more than one.
+    pub(crate) fn saves_itself(&self) -> bool {
+        matches!(self, Command::Move { .. })
+    }
+
+    pub(crate) fn run(self, context: &Context, tasks: &mut Tasks, journal: &mut Journal) -> anyhow::Result<()> {
         match self {
             Command::Add {
                 short,
@@ -85,24 +325,310 @@ impl Command {
                 deadline,
                 priority,
                 tags,
-                
+                parent,
+                depends_on,
+                recur,
+                remind,
+                wait,
             } => {
-                let task = Task::new(short, category, long, deadline, priority, tags_from_comma_separated_string(tags)?)?;
-                tasks.add_task(task);
+                let config = config();
+                let category = category.unwrap_or_else(|| config.category.clone());
+                let priority = priority.unwrap_or(config.priority);
+                if let Some(parent) = parent.filter(|parent| tasks.get_task(*parent).is_none()) {
+                    return Err(anyhow::anyhow!("no active task at ID {parent}"));
+                }
+                let deadline = deadline.map(|deadline| checked_deadline(deadline.into()));
+                let mut task = Task::new(short, category, long, deadline, priority, tags_from_comma_separated_string(tags)?)?;
+                task.parent = parent;
+                task.recurrence = recur;
+                task.add_reminders(&remind);
+                if let Some(wait) = wait {
+                    task.snooze(wait)?;
+                }
+                let id = tasks.add_task(task);
+                tasks.add_dependencies(id, &depends_on)?;
+                println!("Added task with ID {id}.");
             }
             Command::Start { id } => tasks.get_task_mut_err(id)?.start()?,
+            Command::Snooze { id, until } => {
+                let until = Moment::from(until.join(" ").parse::<DateSpecifier>()?);
+                tasks.get_task_mut_err(id)?.snooze(until)?;
+                println!("Snoozed task {id} until {until}.");
+            }
+            Command::Clock { clock } => clock.run(tasks)?,
+            Command::LogTime { id, span } => {
+                let task = tasks.get_task_mut_err(id)?;
+                task.log_time(span)?;
+                println!("Logged {span} on task {id}, {} in total.", Span::whole_minutes(task.time_spent()));
+            }
             Command::Update { id, update } => update.run(id, tasks)?,
-            Command::Print { format, summary, filter, sort } => summary.run(format, tasks, filter.map(tags_from_comma_separated_string).transpose()?, sort)?,
+            Command::Bulk { query, dry_run, update } => {
+                let ids = tasks.iter().filter(|task| query.matches(task)).map(|task| task.id).collect::<Vec<_>>();
+                if ids.is_empty() {
+                    println!("No tasks match the query.");
+                } else if dry_run {
+                    println!("Would update tasks {}.", ids.iter().join(", "));
+                } else {
+                    for id in &ids {
+                        update.clone().run(*id, tasks)?;
+                    }
+                    println!("Updated tasks {}.", ids.iter().join(", "));
+                }
+            }
+            Command::Print { format, summary, filter, query, sort, group_by, archived, all_lists, with_global } => {
+                let config = config();
+                let format = format.unwrap_or_else(|| config.format.clone());
+                let sort = sort.unwrap_or_else(|| config.sort.clone());
+                let filter = filter.or_else(|| config.filter.clone()).map(tags_from_comma_separated_string).transpose()?;
+                let sources = print_sources(context, all_lists, with_global)?;
+                if sources.len() > 1 {
+                    for (title, filepath) in sources {
+                        let other = if filepath == context.filepath { None } else { Some(Tasks::load(&filepath)?) };
+                        print_header(&title);
+                        let tasks = other.as_ref().unwrap_or(tasks);
+                        format.print(summary.select(tasks, filter.clone(), query.as_ref(), &sort, archived), tasks, group_by);
+                    }
+                } else {
+                    format.print(summary.select(tasks, filter, query.as_ref(), &sort, archived), tasks, group_by);
+                }
+                report_dangling_blockers(tasks);
+            }
             Command::Clear => {
                 let num = tasks.num_tasks();
                 tasks.clear();
                 println!("Cleared {num} tasks.")
             }
+            Command::Delete { ids } => {
+                for id in ids {
+                    tasks.delete_task(id)?;
+                    println!("Deleted task {id}.");
+                }
+                report_dangling_blockers(tasks);
+            }
+            Command::Archive { ids } => {
+                for id in ids {
+                    tasks.archive_task(id)?;
+                    println!("Archived task {id}.");
+                }
+            }
+            Command::Restore { id } => {
+                tasks.restore_task(id)?;
+                println!("Restored task {id}.");
+            }
+            Command::Move { ids, to } => {
+                let filepath = context.lists.existing_filepath(&to)?;
+                if filepath == context.filepath {
+                    return Err(anyhow::anyhow!("tasks are already in list {to}"));
+                }
+
+                // the current list is locked already, waiting for the destination could deadlock with a move back
+                let _lock = CacheLock::try_acquire(&filepath).with_context(|| format!("could not lock list {to}"))?;
+                let mut destination = Tasks::load(&filepath)?;
+                let mut destination_journal = Journal::load(&Journal::filepath(&filepath))?;
+                let (snapshot, original_journal) = (tasks.clone(), journal.clone());
+                // links between tasks only survive if both ends move, read them before deletions rewire them
+                let links = ids
+                    .iter()
+                    .filter_map(|id| tasks.get_task(*id))
+                    .map(|task| (task.id, task.parent, task.depends_on.clone()))
+                    .collect::<Vec<_>>();
+                let mut new_ids = HashMap::new();
+                for id in ids {
+                    let mut task = tasks.delete_task(id)?;
+                    task.parent = None;
+                    task.depends_on.clear();
+                    let new_id = destination.add_task(task);
+                    new_ids.insert(id, new_id);
+                    println!("Moved task {id} to list {to}, where it has ID {new_id}.");
+                }
+                for (id, parent, depends_on) in links {
+                    if let Some(new_parent) = parent.and_then(|parent| new_ids.get(&parent)) {
+                        destination.set_parent(new_ids[&id], Some(*new_parent))?;
+                    }
+                    let depends_on = depends_on.iter().filter_map(|blocker| new_ids.get(blocker).copied()).collect::<Vec<_>>();
+                    destination.add_dependencies(new_ids[&id], &depends_on)?;
+                }
+                unblock_dependents(tasks);
+                unblock_dependents(&mut destination);
+                report_dangling_blockers(&destination);
+                report_dangling_blockers(tasks);
+
+                journal.record_across_lists(command_line());
+                destination_journal.record_across_lists(command_line());
+                // the current list goes first and is put back if the destination cannot be saved, so that no task
+                // ends up in both lists
+                save(&context.filepath, tasks.clone(), journal)?;
+                if let Err(error) = save(&filepath, destination, &destination_journal) {
+                    journal.revert_to(original_journal);
+                    save(&context.filepath, snapshot, journal).with_context(|| format!("could not put back tasks moved to list {to}"))?;
+                    return Err(error.context(format!("could not move tasks to list {to}, they were left in place")));
+                }
+            }
+            Command::Lists => {
+                let project = context.project.iter().map(|project| (format!("project {}", project.display()), project.clone()));
+                let names = context.lists.names()?.into_iter().map(|name| {
+                    let filepath = context.lists.filepath(&name);
+                    (name, filepath)
+                });
+                for (name, filepath) in project.chain(names) {
+                    let num_tasks = if filepath == context.filepath {
+                        tasks.num_tasks()
+                    } else if filepath.is_file() {
+                        Tasks::load(&filepath)?.num_tasks()
+                    } else {
+                        0
+                    };
+                    let marker = if filepath == context.filepath { "*" } else { " " };
+                    println!("{marker} {name} ({num_tasks} tasks)");
+                }
+            }
+            Command::List { list } => list.run(&context.lists, &context.filepath)?,
+            Command::Next { query } => {
+                let urgency = &config().urgency;
+                let blocking = tasks.blocking();
+                let next = tasks
+                    .iter()
+                    .filter(|task| !task.is_closed() && !task.is_blocked() && !task.is_waiting())
+                    .filter(|task| query.as_ref().is_none_or(|query| query.matches(task)))
+                    .map(|task| (task, urgency.score(task, &blocking)))
+                    // the lowest ID wins a tie
+                    .rev()
+                    .max_by(|(_, a), (_, b)| a.total_cmp(b));
+                match next {
+                    Some((task, _)) => TaskListFormatter::Long.print_rows(vec![Row::new(task, 0, tasks, &blocking)]),
+                    None => println!("Nothing to do."),
+                }
+            }
+            Command::Report { report } => report.run(&context.filepath, tasks)?,
+            Command::Init { format } => {
+                let directory = std::env::current_dir()?;
+                if let Some(existing) = context.project.as_ref().filter(|project| project.parent() == Some(&directory)) {
+                    return Err(anyhow::anyhow!("{} already exists", existing.display()));
+                }
+                let filepath = project_list_filepath(&directory, format);
+                Tasks::new().save(&filepath)?;
+                println!("Created project list {}.", filepath.display());
+            }
+            Command::Deps { id } => {
+                tasks.find_task(id).ok_or_else(|| anyhow::anyhow!("no task at ID {id}"))?;
+                let format = config().format.clone();
+                let blocking = tasks.blocking();
+                for (title, related) in [(format!("TASK {id} DEPENDS ON"), tasks.upstream(id)), (format!("DEPENDING ON TASK {id}"), tasks.downstream(id))] {
+                    print_header(&title);
+                    let rows = related
+                        .into_iter()
+                        .filter_map(|(id, distance)| tasks.find_task(id).map(|task| Row::new(task, distance - 1, tasks, &blocking)))
+                        .collect::<Vec<_>>();
+                    if rows.is_empty() {
+                        println!("no tasks");
+                    }
+                    format.print_rows(rows);
+                    println!();
+                }
+            }
+            Command::Graph { format, filter, archived } => {
+                let filter = filter.or_else(|| config().filter.clone()).map(tags_from_comma_separated_string).transpose()?;
+                print!("{}", format.render(tasks, filter, archived));
+            }
+            Command::Recur { recur } => recur.run(tasks)?,
+            Command::Remind { due } => remind::run(tasks, due)?,
+            Command::Date { expression } => {
+                let moment = Moment::from(expression.join(" ").parse::<DateSpecifier>()?);
+                let days = (moment.date() - today()).num_days();
+                let relative = match days {
+                    0 => "today".to_string(),
+                    1 => "tomorrow".to_string(),
+                    -1 => "yesterday".to_string(),
+                    days if days > 0 => format!("in {days} days"),
+                    days => format!("{} days ago", -days),
+                };
+                println!("{moment} ({relative})");
+            }
             Command::Tags => {
                 let tags: BTreeSet<Tag> = tasks.iter().flat_map(|task| task.tags.iter()).cloned().collect();
                 println!("{}", tags.iter().join(", "));
             },
+            Command::Undo => journal.undo(tasks)?,
+            Command::Redo => journal.redo(tasks)?,
+            Command::History { num } => journal.print_history(num),
+            Command::Backup { backup } => backup.run(&context.filepath, tasks)?,
+            Command::Storage { storage } => storage.run(&context.filepath, tasks)?,
         };
         Ok(())
     }
 }
+
+/// Commands which run without loading the task list, such that they work whatever state it is in.
+#[derive(Debug, Subcommand)]
+pub(crate) enum Standalone {
+    /// Keeps sending due reminders, checking the task list at a regular interval.
+    Daemon {
+        /// Time between checks, e.g. 30s, 1m or 1h.
+        #[clap(long, default_value = "1m")]
+        interval: Span,
+    },
+
+    /// Validates the task list and repairs any problems found.
+    Doctor {
+        /// Only report problems, without repairing them.
+        #[clap(long)]
+        dry_run: bool,
+    },
+
+    /// Shows or changes settings.
+    Config {
+        #[clap(subcommand)]
+        config: Config,
+    },
+}
+
+impl Standalone {
+    pub(crate) fn run(self, filepath: &Path) -> anyhow::Result<()> {
+        match self {
+            // the daemon loads the task list itself, at every check
+            Standalone::Daemon { interval } => remind::daemon(filepath, interval),
+            // the doctor must be able to open caches which cannot be loaded
+            Standalone::Doctor { dry_run } => {
+                let _lock = CacheLock::acquire(filepath)?;
+                doctor::run(filepath, dry_run)
+            }
+            // a setting which prevents loading the task list, e.g. a wrong storage format, can always be reverted
+            Standalone::Config { config } => {
+                let _lock = CacheLock::acquire(filepath)?;
+                config.run(filepath)
+            }
+        }
+    }
+}
+
+/// Lists the `(title, cache)` of each list to print, given the cross-list options of `print`.
+fn print_sources(context: &Context, all_lists: bool, with_global: bool) -> anyhow::Result<Vec<(String, PathBuf)>> {
+    if !all_lists && !with_global {
+        return Ok(Vec::new());
+    }
+
+    let mut sources = Vec::new();
+    if let Some(project) = &context.project {
+        let directory = project.parent().unwrap_or(project);
+        sources.push((format!("PROJECT {}", directory.display()), project.clone()));
+    }
+
+    let names = if all_lists { context.lists.names()? } else { vec![DEFAULT_LIST.to_string()] };
+    for name in names {
+        sources.push((format!("LIST {name}"), context.lists.filepath(&name)));
+    }
+    Ok(sources)
+}
+
+fn report_dangling_blockers(tasks: &Tasks) {
+    for (blocked, blocking) in tasks.dangling_blockers() {
+        eprintln!("WARNING: task {blocked} depends on task {blocking}, which no longer exists");
+    }
+}
+
+/// Returns tasks whose dependencies have all been completed to the status they had before being blocked.
+pub(crate) fn unblock_dependents(tasks: &mut Tasks) {
+    for id in tasks.unblock_dependents() {
+        println!("Unblocked task {id}, all of its dependencies are completed.");
+    }
+}
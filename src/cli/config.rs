@@ -0,0 +1,122 @@
+use std::path::Path;
+
+use clap::Subcommand;
+
+use crate::{
+    components::time::calendar::Calendar,
+    config::{self, list_filepath, read_table, write_table},
+};
+
+#[derive(Debug, Subcommand)]
+pub(crate) enum Config {
+    /// Shows the value of a setting, as it applies to the current list.
+    Get { key: String },
+
+    /// Changes a setting in the global configuration, or only for the current list.
+    Set {
+        key: String,
+        value: String,
+
+        /// Only change the setting for the current list.
+        #[clap(long)]
+        local: bool,
+    },
+
+    /// Removes a setting from the global configuration, or from that of the current list.
+    Unset {
+        key: String,
+
+        /// Only remove the setting from the current list's configuration.
+        #[clap(long)]
+        local: bool,
+    },
+
+    /// Shows all settings, as they apply to the current list.
+    List,
+}
+
+impl Config {
+    pub(crate) fn run(self, cache: &Path) -> anyhow::Result<()> {
+        match self {
+            Config::Get { key } => {
+                match effective_table()?.remove(&key) {
+                    Some(toml::Value::String(value)) => println!("{value}"),
+                    Some(value) => println!("{value}"),
+                    None => return Err(anyhow::anyhow!("{key} is not set")),
+                }
+            }
+            Config::List => print!("{}", toml::to_string(&effective_table()?)?),
+            Config::Set { key, value, local } => {
+                let filepath = config_filepath(cache, local)?;
+                let mut table = read_table(&filepath)?;
+                table.insert(key.clone(), parse_value(&value));
+                validate(cache, &filepath, &table)?;
+                write_table(&filepath, &table)?;
+                println!("Set {key} in {}.", filepath.display());
+            }
+            Config::Unset { key, local } => {
+                let filepath = config_filepath(cache, local)?;
+                let mut table = read_table(&filepath)?;
+                if table.remove(&key).is_none() {
+                    return Err(anyhow::anyhow!("{key} is not set in {}", filepath.display()));
+                }
+                write_table(&filepath, &table)?;
+                println!("Unset {key} in {}.", filepath.display());
+            }
+        }
+        Ok(())
+    }
+}
+
+fn effective_table() -> anyhow::Result<toml::value::Table> {
+    match toml::Value::try_from(config::config().as_ref())? {
+        toml::Value::Table(table) => Ok(table),
+        _ => unreachable!("config serializes to a table"),
+    }
+}
+
+pub(crate) fn config_filepath(cache: &Path, local: bool) -> anyhow::Result<std::path::PathBuf> {
+    if local {
+        Ok(list_filepath(cache))
+    } else {
+        config::global_filepath().ok_or_else(|| anyhow::anyhow!("no home directory found"))
+    }
+}
+
+/// Interprets a value as TOML if possible, e.g. `50` or `["%d/%m/%Y"]`, and as a plain string otherwise.
+fn parse_value(value: &str) -> toml::Value {
+    toml::from_str::<toml::value::Table>(&format!("value = {value}"))
+        .ok()
+        .and_then(|mut table| table.remove("value"))
+        .unwrap_or_else(|| toml::Value::String(value.to_string()))
+}
+
+/// Checks that a changed configuration file still yields a valid configuration for the current list.
+pub(crate) fn validate(cache: &Path, changed: &Path, table: &toml::value::Table) -> anyhow::Result<()> {
+    let mut tables = Vec::new();
+    for filepath in config::global_filepath().into_iter().chain(std::iter::once(list_filepath(cache))) {
+        tables.push(if filepath == changed { table.clone() } else { read_table(&filepath)? });
+    }
+    // such that a holiday file which cannot be read is refused rather than breaking every later command
+    Calendar::from_config(&config::from_tables(tables)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unreadable_holidays_are_refused() {
+        let cache = std::env::temp_dir().join(format!("wwida-config-{}", std::process::id())).join("cache");
+        let changed = list_filepath(&cache);
+        let mut table = toml::value::Table::new();
+        table.insert("holidays".to_string(), parse_value("/nonexistent/holidays.txt"));
+        assert!(validate(&cache, &changed, &table).is_err());
+
+        table.insert("holidays".to_string(), parse_value("[]"));
+        assert!(validate(&cache, &changed, &table).is_err());
+        table.remove("holidays");
+        assert!(validate(&cache, &changed, &table).is_ok());
+    }
+}
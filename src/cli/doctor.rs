@@ -0,0 +1,133 @@
+use std::path::Path;
+
+use bson::{Bson, Document};
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{
+    components::{category::Category, priority::Priority, status::Status, task::Task, tasks::Tasks},
+    storage::{self, schema},
+};
+
+/// Validates the cache, repairing whatever can be repaired.
+pub(crate) fn run(filepath: &Path, dry_run: bool) -> anyhow::Result<()> {
+    if !filepath.is_file() {
+        println!("No cache at {}, nothing to check.", filepath.display());
+        return Ok(());
+    }
+
+    let mut document = storage::read_document(filepath)?;
+    let mut problems = Vec::new();
+    if schema::version(&document)? < schema::SCHEMA_VERSION {
+        problems.push(format!("upgraded cache from schema version {}", schema::version(&document)?));
+    }
+    schema::migrate(&mut document)?;
+
+    for key in ["tasks", "archived"] {
+        repair_task_list(schema::array_mut(&mut document, key)?, &mut problems);
+    }
+    let mut tasks: Tasks = bson::from_document(document)?;
+    problems.extend(tasks.repair());
+
+    for problem in &problems {
+        println!("{problem}");
+    }
+    if problems.is_empty() {
+        println!("No problems found.");
+    } else if dry_run {
+        println!("Found {} problems, nothing was changed.", problems.len());
+    } else {
+        storage::backup(filepath)?;
+        tasks.save(filepath)?;
+        println!("Repaired {} problems.", problems.len());
+    }
+    Ok(())
+}
+
+fn repair_task_list(tasks: &mut Vec<Bson>, problems: &mut Vec<String>) {
+    let mut repaired = Vec::with_capacity(tasks.len());
+    for (position, task) in tasks.drain(..).enumerate() {
+        let mut task = match task {
+            Bson::Document(task) => task,
+            other => {
+                problems.push(format!("removed entry {position}, which is not a task: {other}"));
+                continue;
+            }
+        };
+        let name = match task.get("id") {
+            Some(id) => format!("task {id}"),
+            None => format!("task at position {position}"),
+        };
+
+        repair_field::<Category>(&mut task, "category", &name, problems);
+        repair_field::<Priority>(&mut task, "priority", &name, problems);
+        repair_statuses(&mut task, &name, problems);
+
+        match bson::from_document::<Task>(task.clone()) {
+            Ok(_) => repaired.push(Bson::Document(task)),
+            Err(error) => problems.push(format!("removed {name}, which could not be repaired: {error}")),
+        }
+    }
+    *tasks = repaired;
+}
+
+/// Resets a field that does not hold a valid value, e.g. an unknown enum variant, to its default.
+fn repair_field<T: DeserializeOwned + Serialize + Default>(
+    task: &mut Document,
+    key: &str,
+    name: &str,
+    problems: &mut Vec<String>,
+) {
+    let valid = task.get(key).map(|value| bson::from_bson::<T>(value.clone()).is_ok()).unwrap_or(false);
+    if !valid {
+        if let Ok(default) = bson::to_bson(&T::default()) {
+            problems.push(format!("{name}: reset invalid {key} to {default}"));
+            task.insert(key, default);
+        }
+    }
+}
+
+fn repair_statuses(task: &mut Document, name: &str, problems: &mut Vec<String>) {
+    let statuses = match task.get_array_mut("statuses") {
+        Ok(statuses) => statuses,
+        Err(_) => {
+            task.insert("statuses", Bson::Array(Vec::new()));
+            task.get_array_mut("statuses").expect("statuses were just inserted")
+        }
+    };
+
+    statuses.retain(|status| {
+        let valid = bson::from_bson::<Status>(status.clone()).is_ok();
+        if !valid {
+            problems.push(format!("{name}: removed invalid status {status}"));
+        }
+        valid
+    });
+
+    if statuses.is_empty() {
+        problems.push(format!("{name}: had no status, set to NOT STARTED"));
+        statuses.push(bson::to_bson(&Status::NotStarted).expect("status is serializable"));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn repair_unknown_variants() {
+        let task = Task::from_strings("fix the doctor", None, None, None, None, vec![]).expect("could not create task");
+        let mut task = bson::to_document(&task).expect("could not serialize");
+        task.insert("category", "Chore");
+        task.insert("statuses", vec![Bson::String("Sleeping".to_string())]);
+
+        let mut tasks = vec![Bson::Document(task), Bson::Int32(5)];
+        let mut problems = Vec::new();
+        repair_task_list(&mut tasks, &mut problems);
+
+        assert_eq!(problems.len(), 4);
+        assert_eq!(tasks.len(), 1);
+        let task: Task = bson::from_bson(tasks.remove(0)).expect("task was not repaired");
+        assert_eq!(task.category, Category::Task);
+        assert!(matches!(task.last_status(), Status::NotStarted));
+    }
+}
@@ -0,0 +1,120 @@
+use std::{collections::HashSet, fmt::Write};
+
+use clap::ValueEnum;
+
+use crate::components::{category::Category, status::Status, tag::Tag, task::Task, tasks::Tasks};
+
+#[derive(Debug, Clone, Copy, Default, ValueEnum)]
+pub(crate) enum GraphFormat {
+    /// Graphviz, e.g. `wwida graph | dot -Tsvg > tasks.svg`.
+    #[default]
+    Dot,
+
+    /// Mermaid flowchart, which renders in Markdown on most code hosting sites.
+    Mermaid,
+}
+
+impl GraphFormat {
+    /// Renders the dependencies between the tasks matching the filter, with edges pointing from a task to the tasks
+    /// it blocks.
+    pub(crate) fn render(&self, tasks: &Tasks, filter: Option<Vec<Tag>>, archived: bool) -> String {
+        let filter: Option<HashSet<Tag>> = filter.map(|v| v.into_iter().collect());
+        let archived = tasks.iter_archived().filter(|_| archived);
+        let nodes = tasks
+            .iter()
+            .chain(archived)
+            .filter(|task| if let Some(filter) = &filter { !filter.is_disjoint(&task.tags) } else { true })
+            .collect::<Vec<&Task>>();
+        let ids = nodes.iter().map(|task| task.id).collect::<HashSet<_>>();
+        let edges = nodes
+            .iter()
+            .flat_map(|task| task.depends_on.iter().filter(|blocker| ids.contains(blocker)).map(|blocker| (*blocker, task.id)))
+            .collect::<Vec<_>>();
+
+        let mut graph = String::new();
+        match self {
+            GraphFormat::Dot => {
+                graph.push_str("digraph wwida {\n    rankdir=LR;\n    node [shape=box, style=\"filled,rounded\", penwidth=2];\n");
+                for task in &nodes {
+                    let label = format!("{}: {}", task.id, task.short).replace('\\', "\\\\").replace('"', "\\\"");
+                    let _ = writeln!(
+                        graph,
+                        "    t{} [label=\"{label}\", fillcolor=\"{}\", color=\"{}\"];",
+                        task.id,
+                        category_colour(&task.category),
+                        status_colour(task.last_status()),
+                    );
+                }
+                for (blocker, blocked) in edges {
+                    let _ = writeln!(graph, "    t{blocker} -> t{blocked};");
+                }
+                graph.push_str("}\n");
+            }
+            GraphFormat::Mermaid => {
+                graph.push_str("flowchart LR\n");
+                for task in &nodes {
+                    let label = format!("{}: {}", task.id, task.short).replace('"', "#quot;");
+                    let _ = writeln!(graph, "    t{}[\"{label}\"]", task.id);
+                    let _ = writeln!(
+                        graph,
+                        "    style t{} fill:{},stroke:{},stroke-width:2px",
+                        task.id,
+                        category_colour(&task.category),
+                        status_colour(task.last_status()),
+                    );
+                }
+                for (blocker, blocked) in edges {
+                    let _ = writeln!(graph, "    t{blocker} --> t{blocked}");
+                }
+            }
+        }
+        graph
+    }
+}
+
+/// Fill colour of a node.
+fn category_colour(category: &Category) -> &'static str {
+    match category {
+        Category::Task => "#e8eaf6",
+        Category::Feature => "#c8e6c9",
+        Category::Bug => "#ffcdd2",
+        Category::Maintenance => "#fff9c4",
+        Category::Documentation => "#bbdefb",
+        Category::Other => "#eeeeee",
+        Category::Message => "#e1bee7",
+    }
+}
+
+/// Border colour of a node.
+fn status_colour(status: &Status) -> &'static str {
+    match status {
+        Status::NotStarted => "#757575",
+        Status::InProgress { .. } => "#1e88e5",
+        Status::BlockedByTask { .. } | Status::BlockedByOther { .. } => "#e53935",
+        Status::Completed { .. } => "#43a047",
+        Status::Discarded { .. } => "#bdbdbd",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn render_filtered_edges() {
+        let mut tasks = Tasks::new();
+        for (short, tags) in [("write \"docs\"", vec!["docs"]), ("review", vec!["docs"]), ("deploy", vec![])] {
+            tasks.add_task(Task::from_strings(short, None, None, None, None, tags).expect("could not create task"));
+        }
+        tasks.add_dependencies(1, &[0]).expect("could not add dependency");
+        tasks.add_dependencies(2, &[1]).expect("could not add dependency");
+
+        let dot = GraphFormat::Dot.render(&tasks, None, false);
+        assert!(dot.contains("t0 [label=\"0: write \\\"docs\\\"\""));
+        assert!(dot.contains("t0 -> t1;") && dot.contains("t1 -> t2;"));
+
+        let mermaid = GraphFormat::Mermaid.render(&tasks, Some(vec!["docs".parse().expect("invalid tag")]), false);
+        assert!(mermaid.contains("t0 --> t1"));
+        assert!(!mermaid.contains("t2"));
+    }
+}
@@ -0,0 +1,80 @@
+use std::path::Path;
+
+use clap::Subcommand;
+
+use crate::{components::tasks::Tasks, storage::{lists::Lists, CacheLock}};
+
+#[derive(Debug, Subcommand)]
+pub(crate) enum List {
+    /// Creates a new, empty list.
+    Create { name: String },
+
+    /// Renames a list.
+    Rename { name: String, new_name: String },
+
+    /// Deletes a list, along with its history and backups.
+    Delete {
+        name: String,
+
+        /// Delete the list even if it still has tasks.
+        #[clap(long)]
+        force: bool,
+    },
+}
+
+impl List {
+    /// Runs the command while `current`, the list being worked on, is locked.
+    pub(crate) fn run(self, lists: &Lists, current: &Path) -> anyhow::Result<()> {
+        // locking the current list a second time would wait on ourselves forever
+        let lock = |filepath: &Path| (filepath != current).then(|| CacheLock::acquire(filepath)).transpose();
+        match self {
+            List::Create { name } => {
+                let filepath = lists.new_filepath(&name)?;
+                Tasks::new().save(&filepath)?;
+                println!("Created list {name}.");
+            }
+            List::Rename { name, new_name } => {
+                let _lock = lock(&lists.existing_filepath(&name)?)?;
+                lists.rename(&name, &new_name)?;
+                println!("Renamed list {name} to {new_name}.");
+            }
+            List::Delete { name, force } => {
+                let filepath = lists.existing_filepath(&name)?;
+                let _lock = lock(&filepath)?;
+                let num_tasks = Tasks::load(&filepath)?.num_tasks();
+                if num_tasks > 0 && !force {
+                    return Err(anyhow::anyhow!("list {name} still has {num_tasks} tasks, use --force to delete it"));
+                }
+                lists.delete(&name)?;
+                println!("Deleted list {name}.");
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rename_and_delete_the_locked_list() {
+        let directory = std::env::temp_dir().join(format!("wwida-list-{}", std::process::id()));
+        std::fs::create_dir_all(&directory).expect("could not create test directory");
+        let lists = Lists::new(directory.join("cache"));
+        List::Create { name: "foo".to_string() }.run(&lists, &lists.filepath("default")).expect("could not create");
+
+        // as when running `wwida --list foo list rename foo bar`, which must not wait on its own lock
+        let current = lists.filepath("foo");
+        let lock = CacheLock::acquire(&current).expect("could not lock");
+        List::Rename { name: "foo".to_string(), new_name: "bar".to_string() }.run(&lists, &current).expect("could not rename");
+        drop(lock);
+
+        let current = lists.filepath("bar");
+        let _lock = CacheLock::acquire(&current).expect("could not lock");
+        List::Delete { name: "bar".to_string(), force: false }.run(&lists, &current).expect("could not delete");
+        assert_eq!(lists.names().expect("could not list"), vec!["default".to_string()]);
+
+        std::fs::remove_dir_all(directory).expect("could not remove test directory");
+    }
+}
@@ -1,15 +1,42 @@
-use std::{path::PathBuf, str::FromStr};
+use std::{
+    path::{Path, PathBuf},
+    str::FromStr,
+};
 
-use clap::Parser;
+use clap::{Parser, Subcommand};
+use itertools::Itertools;
 
-use crate::components::tasks::Tasks;
+use crate::{
+    components::{
+        tasks::Tasks,
+        time::{calendar::check_deadline, moment::Moment},
+    },
+    journal::Journal,
+    storage::{
+        lists::{find_project_list, Lists, DEFAULT_LIST},
+        CacheLock,
+    },
+};
 
-use self::command::Command;
+use self::{
+    command::{Command, Standalone},
+    config::Config,
+};
 
+pub(crate) mod backup;
+pub(crate) mod clock;
 pub(crate) mod command;
+pub(crate) mod config;
+pub(crate) mod doctor;
+pub(crate) mod graph;
+pub(crate) mod list;
+pub(crate) mod recur;
+pub(crate) mod remind;
+pub(crate) mod report;
 pub(crate) mod summary;
 pub(crate) mod update;
 pub(crate) mod sort;
+pub(crate) mod storage;
 
 fn get_filepath() -> PathBuf {
     if let Ok(s) = std::env::var("WWIDA_CACHE") {
@@ -27,16 +54,99 @@ fn get_filepath() -> PathBuf {
 #[derive(Debug, Parser)]
 #[clap(author, version, about, long_about=None)]
 pub(crate) struct Cli {
+    /// Named list to work on instead of the default one.
+    #[clap(long, global = true)]
+    list: Option<String>,
+
+    /// Work on the global default list, even inside a directory with a project list.
+    #[clap(short, long, global = true, conflicts_with = "list")]
+    global: bool,
+
     #[clap(subcommand)]
-    command: Command,
+    command: TopLevel,
+}
+
+#[derive(Debug, Subcommand)]
+enum TopLevel {
+    #[clap(flatten)]
+    Standalone(Standalone),
+
+    #[clap(flatten)]
+    Command(Command),
+}
+
+/// Where the task list a command works on is stored.
+pub(crate) struct Context {
+    pub(crate) filepath: PathBuf,
+    pub(crate) lists: Lists,
+
+    /// Project list found in the working directory or above, if any.
+    pub(crate) project: Option<PathBuf>,
 }
 
 impl Cli {
     pub(crate) fn run(self) -> anyhow::Result<()> {
-        let filepath = get_filepath();
+        let lists = Lists::new(get_filepath());
+        let project = find_project_list(&std::env::current_dir()?);
+        let filepath = match (&self.list, &project) {
+            (Some(name), _) => lists.existing_filepath(name)?,
+            (None, Some(project)) if !self.global => project.clone(),
+            _ => lists.filepath(DEFAULT_LIST),
+        };
+        let journal_filepath = Journal::filepath(&filepath);
+        let loaded = crate::config::load(Some(&filepath));
+        // a configuration which cannot be loaded can still be changed, so that it can be repaired
+        if !matches!(self.command, TopLevel::Standalone(Standalone::Config { config: Config::Set { .. } | Config::Unset { .. } })) {
+            loaded?;
+        }
+
+        let command = match self.command {
+            TopLevel::Standalone(standalone) => return standalone.run(&filepath),
+            TopLevel::Command(command) => command,
+        };
+
+        // held until the end of the load-modify-save cycle
+        let _lock = CacheLock::acquire(&filepath)?;
+
         let mut tasks = Tasks::load(&filepath)?;
-        self.command.run(&mut tasks)?;
-        tasks.save(&filepath)?;
+        let mut journal = Journal::load(&journal_filepath)?;
+
+        let saved = command.is_read_only() || command.saves_itself();
+        let snapshot = command.is_journaled().then(|| tasks.clone());
+        let context = Context { filepath, lists, project };
+        command.run(&context, &mut tasks, &mut journal)?;
+        if let Some(snapshot) = snapshot {
+            journal.record(command_line(), &snapshot)?;
+        }
+
+        if !saved {
+            command::unblock_dependents(&mut tasks);
+            save(&context.filepath, tasks, &journal)?;
+        }
         Ok(())
     }
 }
+
+/// Persists a task list and its journal, backing up the previous version of the list first.
+pub(crate) fn save(filepath: &Path, tasks: Tasks, journal: &Journal) -> anyhow::Result<()> {
+    journal.save(&Journal::filepath(filepath))?;
+    crate::storage::backup(filepath)?;
+    tasks.save(filepath)
+}
+
+/// Applies the configured policy for deadlines on non-working days, telling the user about its outcome if any.
+pub(crate) fn checked_deadline(deadline: Moment) -> Moment {
+    let (deadline, notice) = check_deadline(deadline);
+    if let Some(notice) = notice {
+        println!("{notice}");
+    }
+    deadline
+}
+
+/// Reconstructs the command line the program was called with, for display in the journal.
+pub(crate) fn command_line() -> String {
+    std::env::args()
+        .skip(1)
+        .map(|arg| if arg.contains(char::is_whitespace) { format!("\"{arg}\"") } else { arg })
+        .join(" ")
+}
@@ -0,0 +1,51 @@
+use clap::Subcommand;
+
+use crate::components::{tasks::Tasks, time::recurrence::Recurrence, TaskId};
+
+#[derive(Debug, Subcommand)]
+pub(crate) enum Recur {
+    /// Lists all recurring tasks with their rules.
+    List,
+
+    /// Makes a task recur once it is completed.
+    Set {
+        id: TaskId,
+
+        /// Recurrence rule: daily, weekly/monday,thursday, monthly/n or every/n days after completion.
+        recurrence: Recurrence,
+    },
+
+    /// Stops a task from recurring, the task itself is kept.
+    Stop { id: TaskId },
+}
+
+impl Recur {
+    pub(crate) fn is_read_only(&self) -> bool {
+        matches!(self, Recur::List)
+    }
+
+    pub(crate) fn run(self, tasks: &mut Tasks) -> anyhow::Result<()> {
+        match self {
+            Recur::List => {
+                for task in tasks.iter() {
+                    if let Some(recurrence) = &task.recurrence {
+                        let deadline = task
+                            .deadline
+                            .0
+                            .map(|deadline| format!("DUE {deadline}"))
+                            .unwrap_or_else(|| "NO DEADLINE".to_string());
+                        println!("[ {:^7} ] {:<52} [ {deadline:^20} ] {recurrence}", task.id, task.short.to_string());
+                    }
+                }
+            }
+            Recur::Set { id, recurrence } => tasks.get_task_mut_err(id)?.recurrence = Some(recurrence),
+            Recur::Stop { id } => {
+                if tasks.get_task_mut_err(id)?.recurrence.take().is_none() {
+                    return Err(anyhow::anyhow!("task {id} does not recur"));
+                }
+                println!("Task {id} no longer recurs.");
+            }
+        }
+        Ok(())
+    }
+}
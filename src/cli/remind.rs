@@ -0,0 +1,182 @@
+use std::{collections::BTreeMap, path::Path, process::Command};
+
+use anyhow::Context;
+
+use crate::{
+    components::{
+        reminder::Reminder,
+        task::Task,
+        tasks::Tasks,
+        time::{moment::Moment, now, span::Span},
+        TaskId,
+    },
+    config::config,
+    storage::CacheLock,
+};
+
+/// Lists the reminders still to come, or fires those which are due.
+pub(crate) fn run(tasks: &mut Tasks, due: bool) -> anyhow::Result<()> {
+    if due {
+        let fired = fire_due(tasks)?;
+        println!("Sent {fired} reminders.");
+        return Ok(());
+    }
+
+    let mut upcoming = tasks
+        .iter()
+        .flat_map(|task| task.pending_reminders().into_iter().map(move |(index, at)| (at, task, index)))
+        .collect::<Vec<_>>();
+    upcoming.sort_by_key(|(at, task, _)| (*at, task.id));
+    if upcoming.is_empty() {
+        println!("No upcoming reminders.");
+    }
+    for (at, task, index) in upcoming {
+        let when = Moment::DateTime(at).to_string();
+        println!("[ {:^7} ] {:<52} [ {when:^26} ] {}", task.id, task.short.to_string(), describe(&task.reminders[index]));
+    }
+    Ok(())
+}
+
+/// Sends a notification for every task with a due reminder, marking its due reminders as fired.
+///
+/// A task whose reminders piled up, e.g. while no daemon was running, is only notified once, about the latest one.
+pub(crate) fn fire_due(tasks: &mut Tasks) -> anyhow::Result<usize> {
+    let now = now();
+    let mut due: BTreeMap<TaskId, Vec<usize>> = BTreeMap::new();
+    for task in tasks.iter() {
+        for (index, _) in task.pending_reminders().into_iter().filter(|(_, at)| *at <= now) {
+            due.entry(task.id).or_default().push(index);
+        }
+    }
+
+    let mut fired = 0;
+    for (id, indices) in due {
+        let task = tasks.get_task_mut_err(id)?;
+        let latest = *indices.iter().min_by_key(|index| task.reminders[**index].before).expect("due tasks have due reminders");
+        match notify(task, &task.reminders[latest]) {
+            Ok(true) => {
+                for index in indices {
+                    task.reminders[index].fired = true;
+                }
+                fired += 1;
+            }
+            Ok(false) => (),
+            // carried on with, such that the reminders sent so far are recorded as fired
+            Err(error) => eprintln!("WARNING: {error:#} for task {id}, it will be retried"),
+        }
+    }
+    Ok(fired)
+}
+
+/// Checks the task list for due reminders at every interval, until interrupted.
+///
+/// The cache is only locked while it is being checked, so that other commands can run in between. A failed check is
+/// reported and retried at the next interval.
+pub(crate) fn daemon(filepath: &Path, interval: Span) -> anyhow::Result<()> {
+    if interval.is_zero() {
+        return Err(anyhow::anyhow!("the interval between checks cannot be zero"));
+    }
+    let pause = interval.duration().to_std()?;
+    println!("Checking {} for reminders every {interval}, press Ctrl+C to stop.", filepath.display());
+    loop {
+        if let Err(error) = check(filepath) {
+            eprintln!("WARNING: could not check for reminders, retrying in {interval}: {error}");
+        }
+        std::thread::sleep(pause);
+    }
+}
+
+/// Fires the due reminders of the task list once.
+fn check(filepath: &Path) -> anyhow::Result<()> {
+    let _lock = CacheLock::acquire(filepath)?;
+    let mut tasks = Tasks::load(filepath)?;
+    // saved without a backup, which frequent checks would otherwise push out, nor a journal entry
+    if fire_due(&mut tasks)? > 0 {
+        tasks.save(filepath)?;
+    }
+    Ok(())
+}
+
+fn describe(reminder: &Reminder) -> String {
+    if reminder.before.is_zero() {
+        "DEADLINE".to_string()
+    } else {
+        format!("{} BEFORE DEADLINE", reminder.before)
+    }
+}
+
+/// Runs the configured notify command for a reminder, or prints the reminder if there is none.
+///
+/// Returns whether the notification went out, a failing command leaving the reminder to be retried.
+fn notify(task: &Task, reminder: &Reminder) -> anyhow::Result<bool> {
+    let deadline = task.deadline.0.expect("only tasks with a deadline have pending reminders");
+    let message = if reminder.before.is_zero() {
+        format!("Task {} \"{}\" is due now, {deadline}.", task.id, task.short)
+    } else {
+        format!("Task {} \"{}\" is due in {}, {deadline}.", task.id, task.short, reminder.before)
+    };
+
+    let command = match &config().notify_command {
+        Some(command) => command.clone(),
+        None => {
+            println!("{message}");
+            return Ok(true);
+        }
+    };
+    let status = shell(&command)
+        .env("WWIDA_ID", task.id.to_string())
+        .env("WWIDA_SHORT", task.short.to_string())
+        .env("WWIDA_DEADLINE", deadline.to_string())
+        .env("WWIDA_REMINDER", reminder.before.to_string())
+        .env("WWIDA_MESSAGE", &message)
+        .status()
+        .with_context(|| format!("could not run notify command {command}"))?;
+    if !status.success() {
+        eprintln!("WARNING: notify command failed ({status}) for task {}, it will be retried", task.id);
+    }
+    Ok(status.success())
+}
+
+fn shell(command: &str) -> Command {
+    #[cfg(windows)]
+    let (shell, flag) = ("cmd", "/C");
+    #[cfg(not(windows))]
+    let (shell, flag) = ("sh", "-c");
+
+    let mut process = Command::new(shell);
+    process.args([flag, command]);
+    process
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use super::*;
+
+    #[test]
+    fn fire_due_reminders_once() {
+        let mut tasks = Tasks::new();
+        for deadline in ["2022-01-06T12:00:00Z", "2022-01-06T13:00:00Z", "2022-01-04"] {
+            let mut task = Task::from_strings("call back", None, None, Some(deadline), None, vec![]).expect("could not create task");
+            task.add_reminders(&["1d".parse().expect("invalid span"), "2h".parse().expect("invalid span")]);
+            tasks.add_task(task);
+        }
+
+        // the first reminder of task 0 is due right now, task 1 is an hour short, task 2 has missed all of its own
+        assert_eq!(fire_due(&mut tasks).expect("could not fire"), 2);
+        let fired = |tasks: &Tasks, id| tasks.get_task(id).expect("no task").reminders.iter().map(|reminder| reminder.fired).collect::<Vec<_>>();
+        assert_eq!(fired(&tasks, 0), vec![true, false, false]);
+        assert_eq!(fired(&tasks, 1), vec![false, false, false]);
+        assert_eq!(fired(&tasks, 2), vec![true, true, true]);
+        assert_eq!(fire_due(&mut tasks).expect("could not fire"), 0);
+
+        tasks.get_task_mut(0).expect("no task").set_deadline(Moment::Date(NaiveDate::from_ymd(2022, 1, 5)));
+        assert_eq!(fired(&tasks, 0), vec![false, false, false]);
+
+        // reminders before the range of dates are left out rather than overflowing
+        let task = tasks.get_task_mut(1).expect("no task");
+        task.add_reminders(&[Span::from(chrono::Duration::weeks(100_000_000))]);
+        assert_eq!(task.pending_reminders().len(), 3);
+    }
+}
@@ -0,0 +1,254 @@
+use std::{fmt::Display, path::Path, str::FromStr};
+
+use clap::{Parser, Subcommand, ValueEnum};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    components::{query::Query, tasks::Tasks},
+    config::{config, read_table, value_enum, write_table},
+    format::{GroupBy, TaskListFormatter},
+};
+
+use super::{
+    config::{config_filepath, validate},
+    sort::{SortBy, SortKey},
+    summary::Summary,
+};
+
+/// A saved `print` command, run with `wwida report <NAME>`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub(crate) struct Report {
+    /// Summary to show, as on the command line, e.g. `pending` or `completed days/7`.
+    pub(crate) summary: String,
+
+    /// Query the tasks have to match, see `wwida print --query`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) query: Option<String>,
+
+    /// Sorting rule, defaults to the configured one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) sort: Option<SortBy>,
+
+    /// Output format, defaults to the configured one.
+    #[serde(skip_serializing_if = "Option::is_none", with = "value_enum::option")]
+    pub(crate) format: Option<TaskListFormatter>,
+
+    /// Field to group tasks by, if any.
+    #[serde(skip_serializing_if = "Option::is_none", with = "value_enum::option")]
+    pub(crate) group_by: Option<GroupBy>,
+
+    /// Include archived tasks.
+    pub(crate) archived: bool,
+}
+
+impl Default for Report {
+    fn default() -> Self {
+        Self { summary: "pending".to_string(), query: None, sort: None, format: None, group_by: None, archived: false }
+    }
+}
+
+/// Parses a summary the way `wwida print` does.
+#[derive(Debug, Parser)]
+#[clap(no_binary_name = true)]
+struct SummaryArgs {
+    #[clap(subcommand)]
+    summary: Summary,
+}
+
+impl Report {
+    fn new(summary: &str, query: Option<&str>, sort: SortKey) -> Self {
+        Self { summary: summary.to_string(), query: query.map(str::to_string), sort: Some(SortBy::by(sort)), ..Self::default() }
+    }
+
+    /// Checks that the summary and query can be parsed, returning them.
+    fn parse(&self) -> anyhow::Result<(Summary, Option<Query>)> {
+        let summary = SummaryArgs::try_parse_from(self.summary.split_whitespace())
+            .map_err(|error| anyhow::anyhow!("invalid summary {}: {}", self.summary, error.kind()))?
+            .summary;
+        let query = self.query.as_deref().map(Query::from_str).transpose()?;
+        Ok((summary, query))
+    }
+
+    pub(crate) fn run(&self, tasks: &Tasks) -> anyhow::Result<()> {
+        let (summary, query) = self.parse()?;
+        let config = config();
+        let format = self.format.clone().unwrap_or_else(|| config.format.clone());
+        let sort = self.sort.as_ref().unwrap_or(&config.sort);
+        format.print(summary.select(tasks, None, query.as_ref(), sort, self.archived), tasks, self.group_by);
+        Ok(())
+    }
+}
+
+impl Display for Report {
+    /// Shows the equivalent `print` command.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "print {}", self.summary)?;
+        if let Some(query) = &self.query {
+            write!(f, " --query \"{query}\"")?;
+        }
+        if let Some(sort) = &self.sort {
+            write!(f, " --sort {sort}")?;
+        }
+        if let Some(format) = self.format.as_ref().and_then(|format| format.to_possible_value()) {
+            write!(f, " --format {}", format.get_name())?;
+        }
+        if let Some(group_by) = self.group_by.and_then(|group_by| group_by.to_possible_value()) {
+            write!(f, " --group-by {}", group_by.get_name())?;
+        }
+        if self.archived {
+            write!(f, " --archived")?;
+        }
+        Ok(())
+    }
+}
+
+/// Reports available without any configuration, which saved reports of the same name replace.
+fn builtin_reports() -> Vec<(&'static str, Report)> {
+    vec![
+        ("next", Report::new("pending", Some("not status:blocked"), SortKey::Urgency)),
+        ("overdue", Report::new("overdue", None, SortKey::Deadline)),
+        ("waiting", Report::new("waiting", None, SortKey::Deadline)),
+        ("blocked", Report::new("pending", Some("status:blocked"), SortKey::Id)),
+        ("recently-completed", Report::new("completed days/7", None, SortKey::Id)),
+    ]
+}
+
+/// Finds a saved or built-in report by name.
+fn find_report(name: &str) -> Option<Report> {
+    config()
+        .reports
+        .get(name)
+        .cloned()
+        .or_else(|| builtin_reports().into_iter().find(|(builtin, _)| *builtin == name).map(|(_, report)| report))
+}
+
+#[derive(Debug, Subcommand)]
+pub(crate) enum ReportCommand {
+    /// Saves a report, replacing any report of the same name.
+    Define {
+        /// Name to run the report by, e.g. standup.
+        name: String,
+
+        /// Query the tasks have to match, e.g. "tag:work and not status:blocked".
+        query: Option<String>,
+
+        /// Summary to show, e.g. pending, overdue or "completed days/1".
+        #[clap(long, default_value = "pending")]
+        summary: String,
+
+        /// Sorting rule, e.g. priority,-deadline,id, defaults to the configured one.
+        #[clap(short, long, allow_hyphen_values = true)]
+        sort: Option<SortBy>,
+
+        /// Output format, defaults to the configured one.
+        #[clap(long, value_enum)]
+        format: Option<TaskListFormatter>,
+
+        /// Show tasks in groups, each under a header with its number of tasks.
+        #[clap(long, value_enum)]
+        group_by: Option<GroupBy>,
+
+        /// Include archived tasks.
+        #[clap(short, long)]
+        archived: bool,
+
+        /// Only save the report for the current list.
+        #[clap(long)]
+        local: bool,
+    },
+
+    /// Lists built-in and saved reports.
+    List,
+
+    /// Removes a saved report.
+    Remove {
+        name: String,
+
+        /// Remove the report from the current list's configuration.
+        #[clap(long)]
+        local: bool,
+    },
+
+    /// Runs a report by name, e.g. `wwida report standup`.
+    #[clap(external_subcommand)]
+    Run(Vec<String>),
+}
+
+impl ReportCommand {
+    pub(crate) fn run(self, cache: &Path, tasks: &Tasks) -> anyhow::Result<()> {
+        match self {
+            ReportCommand::Define { name, query, summary, sort, format, group_by, archived, local } => {
+                if !name.chars().all(|c| c.is_alphanumeric() || c == '-') || ["define", "list", "remove", "help"].contains(&name.as_str()) {
+                    return Err(anyhow::anyhow!("{name} cannot be used as a report name"));
+                }
+                let report = Report { summary, query, sort, format, group_by, archived };
+                report.parse()?;
+
+                let filepath = config_filepath(cache, local)?;
+                let mut table = read_table(&filepath)?;
+                let reports = table.entry("reports").or_insert_with(|| toml::Value::Table(Default::default()));
+                reports
+                    .as_table_mut()
+                    .ok_or_else(|| anyhow::anyhow!("reports in {} is not a table", filepath.display()))?
+                    .insert(name.clone(), toml::Value::try_from(&report)?);
+                validate(cache, &filepath, &table)?;
+                write_table(&filepath, &table)?;
+                println!("Saved report {name} in {}.", filepath.display());
+            }
+            ReportCommand::List => {
+                let config = config();
+                for (name, report) in &config.reports {
+                    println!("{name:<20} {report}");
+                }
+                for (name, report) in builtin_reports().into_iter().filter(|(name, _)| !config.reports.contains_key(*name)) {
+                    println!("{name:<20} {report} (built-in)");
+                }
+            }
+            ReportCommand::Remove { name, local } => {
+                let filepath = config_filepath(cache, local)?;
+                let mut table = read_table(&filepath)?;
+                let reports = table.get_mut("reports").and_then(toml::Value::as_table_mut);
+                let removed = reports.and_then(|reports| reports.remove(&name).map(|_| reports.is_empty()));
+                match removed {
+                    None => return Err(anyhow::anyhow!("no report {name} in {}", filepath.display())),
+                    Some(true) => {
+                        table.remove("reports");
+                    }
+                    Some(false) => (),
+                }
+                write_table(&filepath, &table)?;
+                println!("Removed report {name} from {}.", filepath.display());
+            }
+            ReportCommand::Run(args) => {
+                let (name, rest) = args.split_first().expect("external subcommands have a name");
+                if !rest.is_empty() {
+                    return Err(anyhow::anyhow!("reports take no arguments, got {}", rest.join(" ")));
+                }
+                let report = find_report(name)
+                    .ok_or_else(|| anyhow::anyhow!("no report {name}, see `wwida report list`"))?;
+                report.run(tasks)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builtin_reports_parse() {
+        for (name, report) in builtin_reports() {
+            assert!(report.parse().is_ok(), "{name}");
+        }
+        let report = Report { summary: "completed days/1".to_string(), format: Some(TaskListFormatter::Long), ..Report::default() };
+        assert_eq!(report.to_string(), "print completed days/1 --format long");
+        assert!(Report { summary: "finished".to_string(), ..Report::default() }.parse().is_err());
+
+        let table: toml::value::Table = toml::from_str("summary = \"due this-week\"\nsort = \"deadline\"").expect("invalid toml");
+        let report: Report = toml::Value::Table(table).try_into().expect("invalid report");
+        assert_eq!(report.sort, Some(SortBy::by(SortKey::Deadline)));
+    }
+}
@@ -1,20 +1,137 @@
-use std::str::FromStr;
+use std::{cmp::Ordering, collections::HashMap, fmt::Display, str::FromStr};
 
 use clap::ValueEnum;
-use serde::{Serialize, Deserialize};
+use itertools::Itertools;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize, ValueEnum)]
-#[serde(rename_all = "kebab-case")]
-pub(crate) enum SortBy {
+use crate::{
+    components::{status::Status, task::Task, tasks::Tasks, TaskId},
+    config::config,
+};
+
+/// Field tasks can be sorted by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub(crate) enum SortKey {
     Id,
     Category,
     Priority,
-    Deadline
+    Deadline,
+    Status,
+    Age,
+    Tag,
+    Urgency,
+}
+
+impl SortKey {
+    /// Direction used when none is given: highest priority, urgency and age first, everything else ascending.
+    fn default_direction(self) -> Direction {
+        match self {
+            SortKey::Priority | SortKey::Urgency | SortKey::Age => Direction::Descending,
+            _ => Direction::Ascending,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Direction {
+    Ascending,
+    Descending,
+}
+
+/// Where tasks without a deadline go when sorting by deadline, whatever its direction.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub(crate) enum Placement {
+    #[default]
+    First,
+    Last,
+}
+
+/// One key of a sorting rule, e.g. `-deadline`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct SortRule {
+    key: SortKey,
+    direction: Direction,
+}
+
+impl SortRule {
+    fn compare(&self, a: &Task, b: &Task, urgency: &HashMap<TaskId, f64>, no_deadline: Placement) -> Ordering {
+        let ordering = match self.key {
+            SortKey::Id => a.id.cmp(&b.id),
+            SortKey::Category => a.category.cmp(&b.category),
+            SortKey::Priority => a.priority.cmp(&b.priority),
+            SortKey::Deadline => match (a.deadline.0, b.deadline.0) {
+                (Some(a), Some(b)) => a.cmp(&b),
+                (None, None) => Ordering::Equal,
+                // placed regardless of the direction
+                (None, Some(_)) => return if no_deadline == Placement::First { Ordering::Less } else { Ordering::Greater },
+                (Some(_), None) => return if no_deadline == Placement::First { Ordering::Greater } else { Ordering::Less },
+            },
+            SortKey::Status => status_rank(a).cmp(&status_rank(b)),
+            // tasks of unknown age count as the oldest
+            SortKey::Age => b.created.is_some().cmp(&a.created.is_some()).then_with(|| b.created.cmp(&a.created)),
+            SortKey::Tag => {
+                let (a, b) = (a.tags.iter().min(), b.tags.iter().min());
+                (a.is_none(), a).cmp(&(b.is_none(), b))
+            }
+            SortKey::Urgency => urgency[&a.id].total_cmp(&urgency[&b.id]),
+        };
+        match self.direction {
+            Direction::Ascending => ordering,
+            Direction::Descending => ordering.reverse(),
+        }
+    }
 }
 
+/// Position of a task's status in its lifecycle.
+fn status_rank(task: &Task) -> u8 {
+    match task.last_status() {
+        Status::NotStarted => 0,
+        Status::InProgress { .. } => 1,
+        Status::BlockedByTask { .. } | Status::BlockedByOther { .. } => 2,
+        Status::Completed { .. } => 3,
+        Status::Discarded { .. } => 4,
+    }
+}
+
+/// Sorting rule of `print`, a comma-separated list of keys such as `priority,-deadline,id`.
+///
+/// Later keys break ties of earlier ones, and tasks still tied keep the order of their IDs. A key prefixed with `+`
+/// sorts in ascending order and one prefixed with `-` in descending order; without a prefix, priority, urgency and age
+/// sort highest first and the other keys ascending.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct SortBy(Vec<SortRule>);
+
 impl Default for SortBy {
     fn default() -> Self {
-        Self::Priority
+        Self::by(SortKey::Priority)
+    }
+}
+
+impl SortBy {
+    /// Sorts by a single key in its default direction.
+    pub(crate) fn by(key: SortKey) -> Self {
+        Self(vec![SortRule { key, direction: key.default_direction() }])
+    }
+
+    /// Stably sorts tasks of a list by the rule, ties keeping the order of their IDs.
+    pub(crate) fn sort(&self, tasks: &mut [&Task], context: &Tasks) {
+        let config = config();
+        let no_deadline = config.no_deadline;
+        // scored up front, rather than at every comparison
+        let urgency = if self.0.iter().any(|rule| rule.key == SortKey::Urgency) {
+            let blocking = context.blocking();
+            tasks.iter().map(|task| (task.id, config.urgency.score(task, &blocking))).collect()
+        } else {
+            HashMap::new()
+        };
+        tasks.sort_by_key(|task| task.id);
+        tasks.sort_by(|a, b| {
+            self.0
+                .iter()
+                .map(|rule| rule.compare(a, b, &urgency, no_deadline))
+                .find(|ordering| ordering.is_ne())
+                .unwrap_or(Ordering::Equal)
+        });
     }
 }
 
@@ -22,12 +139,80 @@ impl FromStr for SortBy {
     type Err = anyhow::Error;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        match s {
-            "id" => Ok(Self::Id),
-            "category" => Ok(Self::Category),
-            "priority" => Ok(Self::Priority),
-            "deadline" => Ok(Self::Deadline),
-            _ => Err(anyhow::anyhow!("cannot interpret {s} as a sorting rule"))
+        let rules = s
+            .split(',')
+            .map(|key| {
+                let key = key.trim();
+                let (direction, name) = match key.split_at(key.len().min(1)) {
+                    ("+", name) => (Some(Direction::Ascending), name),
+                    ("-", name) => (Some(Direction::Descending), name),
+                    _ => (None, key),
+                };
+                let key = SortKey::from_str(name, true).map_err(|_| {
+                    anyhow::anyhow!(
+                        "cannot interpret {s} as a sorting rule; use keys among {}, optionally prefixed with + or -",
+                        SortKey::value_variants().iter().filter_map(ValueEnum::to_possible_value).map(|value| value.get_name()).join(", ")
+                    )
+                })?;
+                Ok(SortRule { key, direction: direction.unwrap_or_else(|| key.default_direction()) })
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        Ok(Self(rules))
+    }
+}
+
+impl Display for SortBy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let rules = self.0.iter().map(|rule| {
+            let name = rule.key.to_possible_value().expect("sort keys have no skipped variants").get_name();
+            match rule.direction {
+                direction if direction == rule.key.default_direction() => name.to_string(),
+                Direction::Ascending => format!("+{name}"),
+                Direction::Descending => format!("-{name}"),
+            }
+        });
+        write!(f, "{}", rules.format(","))
+    }
+}
+
+impl Serialize for SortBy {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for SortBy {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::components::tasks::Tasks;
+
+    use super::*;
+
+    #[test]
+    fn multi_key_sort() {
+        let sort = SortBy::from_str("priority, -deadline,+id").expect("could not parse");
+        assert_eq!(sort.to_string(), "priority,-deadline,id");
+        assert_eq!(SortBy::from_str("Age").expect("could not parse").to_string(), "age");
+        assert!(SortBy::from_str("priority,").is_err() && SortBy::from_str("size").is_err());
+
+        let mut list = Tasks::new();
+        for (priority, deadline) in [("low", None), ("high", Some("2022-01-10")), ("high", None), ("high", Some("2022-01-07")), ("low", None)] {
+            list.add_task(Task::from_strings("task", None, None, deadline, Some(priority), vec![]).expect("could not create task"));
         }
+        let ids = |sort: &str| {
+            let mut tasks = list.iter().rev().collect::<Vec<_>>();
+            SortBy::from_str(sort).expect("could not parse").sort(&mut tasks, &list);
+            tasks.iter().map(|task| task.id).collect::<Vec<_>>()
+        };
+        // ties keep the order of their IDs, and tasks without a deadline come first either way by default
+        assert_eq!(ids("priority"), vec![1, 2, 3, 0, 4]);
+        assert_eq!(ids("priority,deadline"), vec![2, 3, 1, 0, 4]);
+        assert_eq!(ids("priority,-deadline"), vec![2, 1, 3, 0, 4]);
+        assert_eq!(ids("+priority,-id"), vec![4, 0, 3, 2, 1]);
     }
-}
\ No newline at end of file
+}
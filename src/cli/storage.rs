@@ -0,0 +1,47 @@
+use std::path::{Path, PathBuf};
+
+use clap::Subcommand;
+
+use crate::{components::tasks::Tasks, storage::StorageFormat};
+
+#[derive(Debug, Subcommand)]
+pub(crate) enum Storage {
+    /// Shows where and how the task list is stored.
+    Info,
+
+    /// Copies the task list into a new file, possibly using a different storage format.
+    Convert {
+        /// Destination file, its extension selects the format: .json, .sqlite or anything else for BSON.
+        destination: PathBuf,
+
+        /// Overrides the format chosen from the destination's extension.
+        #[clap(long, value_enum)]
+        format: Option<StorageFormat>,
+
+        /// Overwrite the destination if it already exists.
+        #[clap(long)]
+        force: bool,
+    },
+}
+
+impl Storage {
+    pub(crate) fn run(self, filepath: &Path, tasks: &Tasks) -> anyhow::Result<()> {
+        match self {
+            Storage::Info => {
+                println!("{} ({})", filepath.display(), StorageFormat::from_filepath(filepath));
+            }
+            Storage::Convert { destination, format, force } => {
+                if destination.exists() && !force {
+                    return Err(anyhow::anyhow!("{} already exists, use --force to overwrite", destination.display()));
+                }
+                if let Some(parent) = destination.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+                    std::fs::create_dir_all(parent)?;
+                }
+                let format = format.unwrap_or_else(|| StorageFormat::from_filepath(&destination));
+                format.backend().write(&destination, &tasks.to_document()?)?;
+                println!("Converted {} tasks to {} ({format}).", tasks.num_tasks(), destination.display());
+            }
+        }
+        Ok(())
+    }
+}
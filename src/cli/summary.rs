@@ -3,48 +3,67 @@ use std::collections::HashSet;
 use clap::Subcommand;
 
 use crate::{
-    components::{time::duration::TimeInterval, tag::Tag, task::Task, TaskId, tasks::Tasks, outcome::Outcome},
-    format::TaskListFormatter,
+    components::{query::Query, time::duration::TimeInterval, tag::Tag, task::Task, tasks::Tasks, outcome::Outcome},
 };
 
 use super::sort::SortBy;
 
 #[derive(Debug, Subcommand)]
 pub(crate) enum Summary {
-    /// Show all items still pending.
+    /// Show all items still pending, except snoozed ones.
     Pending,
 
-    /// Show all items completed in the past duration.
+    /// Show all snoozed items, which are hidden from pending items until their wait date.
+    Waiting,
+
+    /// Show all items completed within a time interval.
     Completed {
-        /// Time interval to go back.
+        /// Time interval, e.g. days/3, months/1, last-month, since:<DATE>, until:<DATE> or <DATE>..<DATE>.
         interval: TimeInterval,
 
         /// Completion outcome
         #[clap(short, long, value_enum)]
         outcome: Option<Outcome>
     },
+
+    /// Show all pending items due within a time interval.
+    Due {
+        /// Time interval, e.g. this-week, next-month, until:friday or <DATE>..<DATE>.
+        interval: TimeInterval,
+    },
+
+    /// Show all pending items past their deadline.
+    Overdue,
 }
 
 impl Summary {
-    pub(crate) fn run(self, format: TaskListFormatter, tasks: &Tasks, filter: Option<Vec<Tag>>, sort: SortBy) -> anyhow::Result<()> {
+    /// Selects the tasks of the summary, in the order they are printed in.
+    pub(crate) fn select<'t>(
+        &self,
+        tasks: &'t Tasks,
+        filter: Option<Vec<Tag>>,
+        query: Option<&Query>,
+        sort: &SortBy,
+        archived: bool,
+    ) -> Vec<&'t Task> {
         let filter: Option<HashSet<Tag>> = filter.map(|v| v.into_iter().collect());
 
-        let mut tasks = tasks.iter().enumerate()
-            .filter(|(_, task)| if let Some(filter) = &filter { !filter.is_disjoint(&task.tags) } else { true })
-            .filter(|(_, task)| match &self {
-                Summary::Pending => !task.is_completed(),
-                Summary::Completed { interval, outcome } => task.is_completed_in_past(interval) && if let Some(outcome) = outcome { task.outcome().expect("task is guaranteed to be completed") == *outcome } else { true },
-            })
-            .collect::<Vec<(TaskId, &Task)>>();
+        let context = tasks;
+        let archived = tasks.iter_archived().filter(|_| archived);
 
-        match sort {
-            SortBy::Id => (),  // already sorted by ID
-            SortBy::Category => tasks.sort_unstable_by(|(_, ta), (_, tb)| ta.category.cmp(&tb.category)),
-            SortBy::Priority => tasks.sort_unstable_by(|(_, ta), (_, tb)| tb.priority.cmp(&ta.priority)),
-            SortBy::Deadline => tasks.sort_unstable_by(|(_, ta), (_, tb)| ta.deadline.cmp(&tb.deadline)),
-        }
+        let mut tasks = tasks.iter().chain(archived)
+            .filter(|task| if let Some(filter) = &filter { !filter.is_disjoint(&task.tags) } else { true })
+            .filter(|task| query.is_none_or(|query| query.matches(task)))
+            .filter(|task| match self {
+                Summary::Pending => !task.is_completed() && !task.is_waiting(),
+                Summary::Waiting => task.is_waiting(),
+                Summary::Completed { interval, outcome } => task.is_completed_within(interval) && if let Some(outcome) = outcome { task.outcome().expect("task is guaranteed to be completed") == *outcome } else { true },
+                Summary::Due { interval } => !task.is_closed() && task.is_due_within(interval),
+                Summary::Overdue => task.is_overdue(),
+            })
+            .collect::<Vec<&Task>>();
 
-        format.print(tasks);
-        Ok(())
+        sort.sort(&mut tasks, context);
+        tasks
     }
 }
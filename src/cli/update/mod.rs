@@ -1,14 +1,15 @@
 use clap::Subcommand;
 
 use crate::components::{
-    category::Category, short_string::ShortString, tasks::Tasks, time::date_specifier::DateSpecifier, TaskId, priority::Priority, tag::tags_from_comma_separated_string,
+    category::Category, short_string::ShortString, tasks::Tasks, time::{date_specifier::DateSpecifier, span::Span}, TaskId, priority::Priority, tag::tags_from_comma_separated_string,
 };
 
 use self::status::StatusUpdate;
+use super::checked_deadline;
 
 pub(crate) mod status;
 
-#[derive(Debug, Subcommand)]
+#[derive(Debug, Clone, Subcommand)]
 pub(crate) enum Update {
     /// Updates the tasks's short description.
     Short { short: ShortString },
@@ -37,6 +38,12 @@ pub(crate) enum Update {
     /// Removes the task's long description.
     DiscardDeadline,
 
+    /// Hides the task from pending tasks until the given date.
+    Wait { wait: DateSpecifier },
+
+    /// Shows the task among pending tasks again.
+    DiscardWait,
+
     /// Update the task's priority.
     Priority { priority: Priority },
 
@@ -45,6 +52,33 @@ pub(crate) enum Update {
 
     /// Remove one or more tags.
     RemoveTags { tags: String },
+
+    /// Makes the task depend on one or more other tasks, blocking it until they are completed.
+    AddDependencies {
+        #[clap(required = true)]
+        ids: Vec<TaskId>,
+    },
+
+    /// Removes one or more dependencies of the task.
+    RemoveDependencies {
+        #[clap(required = true)]
+        ids: Vec<TaskId>,
+    },
+
+    /// Makes the task a subtask of another task.
+    Parent { parent: TaskId },
+
+    /// Makes the task a top-level task again.
+    DiscardParent,
+
+    /// Adds reminders some time before the deadline, e.g. 1d 2h.
+    Remind {
+        #[clap(required = true)]
+        spans: Vec<Span>,
+    },
+
+    /// Removes all reminders but the one at the deadline.
+    DiscardReminders,
 }
 
 impl Update {
@@ -55,8 +89,10 @@ impl Update {
             Update::DiscardLong => tasks.get_task_mut_err(id)?.unset_long(),
             Update::Category { category } => tasks.get_task_mut_err(id)?.set_category(category),
             Update::Status { status } => status.run(id, tasks)?,
-            Update::Deadline { deadline } => tasks.get_task_mut_err(id)?.set_deadline(deadline),
+            Update::Deadline { deadline } => tasks.get_task_mut_err(id)?.set_deadline(checked_deadline(deadline.into())),
             Update::DiscardDeadline => tasks.get_task_mut_err(id)?.unset_deadline(),
+            Update::Wait { wait } => tasks.get_task_mut_err(id)?.snooze(wait)?,
+            Update::DiscardWait => tasks.get_task_mut_err(id)?.wait = None,
             Update::Priority { priority } => tasks.get_task_mut_err(id)?.set_priority(priority),
             Update::AddTags { tags } => {
                 let tags = tags_from_comma_separated_string(tags)?;
@@ -66,6 +102,12 @@ impl Update {
                 let tags = tags_from_comma_separated_string(tags)?;
                 tasks.get_task_mut_err(id)?.remove_tags(&tags);
             },
+            Update::AddDependencies { ids } => tasks.add_dependencies(id, &ids)?,
+            Update::RemoveDependencies { ids } => tasks.remove_dependencies(id, &ids)?,
+            Update::Parent { parent } => tasks.set_parent(id, Some(parent))?,
+            Update::DiscardParent => tasks.set_parent(id, None)?,
+            Update::Remind { spans } => tasks.get_task_mut_err(id)?.add_reminders(&spans),
+            Update::DiscardReminders => tasks.get_task_mut_err(id)?.discard_reminders(),
         };
         Ok(())
     }
@@ -1,14 +1,21 @@
 use clap::Subcommand;
+use itertools::Itertools;
 
-use crate::components::{outcome::Outcome, short_string::ShortString, status::Status, tasks::Tasks, TaskId, time::date_specifier::DateSpecifier};
+use crate::{
+    cli::checked_deadline,
+    components::{outcome::Outcome, short_string::ShortString, status::Status, tasks::Tasks, TaskId, time::date_specifier::DateSpecifier},
+};
 
-#[derive(Debug, Subcommand)]
+#[derive(Debug, Clone, Subcommand)]
 pub(crate) enum StatusUpdate {
     /// Task is in progress.
     InProgress,
 
-    /// Task is blocked by another task.
-    BlockedByTask { id: TaskId },
+    /// Task depends on other tasks, and is blocked until they are all completed.
+    BlockedByTask {
+        #[clap(required = true)]
+        ids: Vec<TaskId>,
+    },
 
     /// Task is blocked for some reason.
     BlockedByOther { reason: ShortString },
@@ -20,24 +27,72 @@ pub(crate) enum StatusUpdate {
 
         #[clap(default_value = "today")]
         date: DateSpecifier,
+
+        /// Also complete all open subtasks, otherwise they have to be closed first.
+        #[clap(long)]
+        with_subtasks: bool,
     },
 
-    /// Task has been discarded for some reason.
+    /// Task has been discarded for some reason, along with all of its open subtasks.
     Discarded { reason: ShortString },
 }
 
 impl StatusUpdate {
     pub(crate) fn run(self, id: TaskId, tasks: &mut Tasks) -> anyhow::Result<()> {
+        // fail before touching any subtasks
+        tasks.get_task_mut_err(id)?;
         let status = match self {
             StatusUpdate::InProgress => Status::in_progress(),
-            StatusUpdate::BlockedByTask { id: blocking_id } => {
-                Status::blocked_by_task(blocking_id, tasks).expect("could not set status")
+            StatusUpdate::BlockedByTask { ids } => {
+                tasks.add_dependencies(id, &ids)?;
+                if tasks.open_blockers(id).is_empty() {
+                    println!("Task {id} only depends on completed tasks, it is not blocked.");
+                }
+                return Ok(());
             }
             StatusUpdate::BlockedByOther { reason } => Status::blocked_by_other(reason),
-            StatusUpdate::Completed { outcome, date } => Status::completed(outcome, date.into()),
-            StatusUpdate::Discarded { reason } => Status::discarded(reason),
+            StatusUpdate::Completed { outcome, date, with_subtasks } => {
+                let status = Status::completed(outcome, date.into());
+                let open = tasks.open_descendants(id);
+                if !open.is_empty() && !with_subtasks {
+                    return Err(anyhow::anyhow!(
+                        "task {id} has open subtasks {}, close them first or pass --with-subtasks",
+                        open.iter().join(", ")
+                    ));
+                }
+                close_subtasks(tasks, open.clone(), &status, "Completed")?;
+                tasks.get_task_mut_err(id)?.set_status(status);
+                for completed in std::iter::once(id).chain(open) {
+                    add_next_occurrence(tasks, completed)?;
+                }
+                return Ok(());
+            }
+            StatusUpdate::Discarded { reason } => {
+                let status = Status::discarded(reason);
+                let open = tasks.open_descendants(id);
+                close_subtasks(tasks, open, &status, "Discarded")?;
+                status
+            }
         };
         tasks.get_task_mut_err(id)?.set_status(status);
         Ok(())
     }
 }
+
+fn close_subtasks(tasks: &mut Tasks, ids: Vec<TaskId>, status: &Status, verb: &str) -> anyhow::Result<()> {
+    for id in ids {
+        tasks.get_task_mut_err(id)?.set_status(status.clone());
+        println!("{verb} subtask {id}.");
+    }
+    Ok(())
+}
+
+fn add_next_occurrence(tasks: &mut Tasks, id: TaskId) -> anyhow::Result<()> {
+    if let Some(next) = tasks.add_next_occurrence(id)? {
+        let task = tasks.get_task_mut_err(next)?;
+        let deadline = checked_deadline(task.deadline.0.expect("occurrences have a deadline"));
+        task.set_deadline(deadline);
+        println!("Task {id} recurs, added its next occurrence with ID {next}, due {deadline}.");
+    }
+    Ok(())
+}
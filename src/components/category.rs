@@ -4,8 +4,9 @@ use clap::clap_derive::ValueEnum;
 use serde::{Deserialize, Serialize};
 
 #[non_exhaustive]
-#[derive(Debug, Serialize, Deserialize, Clone, ValueEnum, PartialEq, PartialOrd, Eq, Ord)]
+#[derive(Debug, Default, Serialize, Deserialize, Clone, ValueEnum, PartialEq, PartialOrd, Eq, Ord)]
 pub(crate) enum Category {
+    #[default]
     Task,
     Feature,
     Bug,
@@ -45,9 +46,3 @@ impl FromStr for Category {
         }
     }
 }
-
-impl Default for Category {
-    fn default() -> Self {
-        Self::Task
-    }
-}
@@ -1,5 +1,6 @@
-use chrono::NaiveDate;
 use serde::{Serialize, Deserialize};
 
+use super::time::moment::Moment;
+
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
-pub(crate) struct Deadline(pub(crate) Option<NaiveDate>);
\ No newline at end of file
+pub(crate) struct Deadline(pub(crate) Option<Moment>);
@@ -6,7 +6,11 @@ pub(crate) mod task;
 pub(crate) mod tasks;
 pub(crate) mod time;
 pub(crate) mod priority;
+pub(crate) mod query;
 pub(crate) mod tag;
 pub(crate) mod deadline;
+pub(crate) mod reminder;
+pub(crate) mod time_entry;
+pub(crate) mod urgency;
 
 pub(crate) type TaskId = usize;
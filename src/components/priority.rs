@@ -3,11 +3,12 @@ use std::str::FromStr;
 use clap::ValueEnum;
 use serde::{Serialize, Deserialize};
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize, ValueEnum, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, ValueEnum, PartialEq, Eq, PartialOrd, Ord)]
 #[serde(rename_all = "kebab-case")]
 pub(crate) enum Priority {
     VeryLow = 1,
     Low = 2,
+    #[default]
     Normal = 3,
     High = 4,
     VeryHigh = 5,
@@ -39,9 +40,3 @@ impl FromStr for Priority {
         }
     }
 }
-
-impl Default for Priority {
-    fn default() -> Self {
-        Self::Normal
-    }
-}
\ No newline at end of file
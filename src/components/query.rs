@@ -0,0 +1,354 @@
+use std::{cmp::Ordering, str::FromStr};
+
+use chrono::NaiveDate;
+use regex::{Regex, RegexBuilder};
+
+use super::{
+    category::Category,
+    priority::Priority,
+    status::Status,
+    tag::Tag,
+    task::Task,
+    time::{date_specifier::DateSpecifier, duration::TimeInterval, moment::Moment},
+    TaskId,
+};
+
+/// A condition on tasks, e.g. `tag:work and not (status:blocked or due>friday)`.
+///
+/// Conditions next to each other must all hold, as if joined by `and`, which binds tighter than `or`.
+#[derive(Debug)]
+pub(crate) enum Query {
+    And(Box<Query>, Box<Query>),
+    Or(Box<Query>, Box<Query>),
+    Not(Box<Query>),
+    Tag(Tag),
+    Category(Category),
+    Priority(Comparison, Priority),
+    Status(StatusFilter),
+    Date(DateField, DateCondition),
+    /// Matched against the short and long descriptions.
+    Text(Regex),
+    Ids(Vec<TaskId>),
+    Parent(TaskId),
+    Overdue,
+    Waiting,
+    Recurring,
+    ClockedIn,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Comparison {
+    Less,
+    LessOrEqual,
+    Equal,
+    GreaterOrEqual,
+    Greater,
+}
+
+impl Comparison {
+    fn holds<T: Ord>(&self, value: T, reference: T) -> bool {
+        let ordering = value.cmp(&reference);
+        match self {
+            Comparison::Less => ordering == Ordering::Less,
+            Comparison::LessOrEqual => ordering != Ordering::Greater,
+            Comparison::Equal => ordering == Ordering::Equal,
+            Comparison::GreaterOrEqual => ordering != Ordering::Less,
+            Comparison::Greater => ordering == Ordering::Greater,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum StatusFilter {
+    NotStarted,
+    InProgress,
+    Blocked,
+    Completed,
+    Discarded,
+    /// Neither completed nor discarded.
+    Open,
+    Closed,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum DateField {
+    Due,
+    Created,
+    Completed,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum DateCondition {
+    Within(TimeInterval),
+    Compare(Comparison, NaiveDate),
+    Any,
+    None,
+}
+
+impl Query {
+    pub(crate) fn matches(&self, task: &Task) -> bool {
+        match self {
+            Query::And(a, b) => a.matches(task) && b.matches(task),
+            Query::Or(a, b) => a.matches(task) || b.matches(task),
+            Query::Not(query) => !query.matches(task),
+            Query::Tag(tag) => task.tags.contains(tag),
+            Query::Category(category) => task.category == *category,
+            Query::Priority(comparison, priority) => comparison.holds(task.priority, *priority),
+            Query::Status(filter) => match (filter, task.last_status()) {
+                (StatusFilter::NotStarted, Status::NotStarted)
+                | (StatusFilter::InProgress, Status::InProgress { .. })
+                | (StatusFilter::Blocked, Status::BlockedByTask { .. } | Status::BlockedByOther { .. })
+                | (StatusFilter::Completed, Status::Completed { .. })
+                | (StatusFilter::Discarded, Status::Discarded { .. }) => true,
+                (StatusFilter::Open, _) => !task.is_closed(),
+                (StatusFilter::Closed, _) => task.is_closed(),
+                _ => false,
+            },
+            Query::Date(field, condition) => {
+                let date = match field {
+                    DateField::Due => task.deadline.0,
+                    DateField::Created => task.created,
+                    DateField::Completed => match task.last_status() {
+                        Status::Completed { date, .. } => Some(*date),
+                        _ => None,
+                    },
+                };
+                match (condition, date.as_ref().map(Moment::date)) {
+                    (DateCondition::Within(interval), Some(date)) => interval.contains(&date),
+                    (DateCondition::Compare(comparison, reference), Some(date)) => comparison.holds(date, *reference),
+                    (DateCondition::Any, date) => date.is_some(),
+                    (DateCondition::None, date) => date.is_none(),
+                    (_, None) => false,
+                }
+            }
+            Query::Text(regex) => regex.is_match(&task.short.to_string()) || task.long.as_ref().is_some_and(|long| regex.is_match(long)),
+            Query::Ids(ids) => ids.contains(&task.id),
+            Query::Parent(parent) => task.parent == Some(*parent),
+            Query::Overdue => task.is_overdue(),
+            Query::Waiting => task.is_waiting(),
+            Query::Recurring => task.recurrence.is_some(),
+            Query::ClockedIn => task.is_clocked_in(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Open,
+    Close,
+    Word(String),
+}
+
+/// Splits a query into words and parentheses, double quotes keeping spaces and parentheses within a word.
+fn tokenize(s: &str) -> anyhow::Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut word = String::new();
+    let mut quoted = false;
+    let mut chars = s.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => quoted = !quoted,
+            // only quotes are escaped, such that regular expressions keep their backslashes
+            '\\' if quoted && chars.peek() == Some(&'"') => word.push(chars.next().expect("peeked a quote")),
+            _ if quoted => word.push(c),
+            '(' | ')' => {
+                if !word.is_empty() {
+                    tokens.push(Token::Word(std::mem::take(&mut word)));
+                }
+                tokens.push(if c == '(' { Token::Open } else { Token::Close });
+            }
+            c if c.is_whitespace() => {
+                if !word.is_empty() {
+                    tokens.push(Token::Word(std::mem::take(&mut word)));
+                }
+            }
+            c => word.push(c),
+        }
+    }
+    if quoted {
+        return Err(anyhow::anyhow!("unterminated quote in query {s}"));
+    }
+    if !word.is_empty() {
+        tokens.push(Token::Word(word));
+    }
+    Ok(tokens)
+}
+
+/// Recursive descent parser over the tokens of a query.
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn is_keyword(&self, keyword: &str) -> bool {
+        matches!(self.peek(), Some(Token::Word(word)) if word.eq_ignore_ascii_case(keyword))
+    }
+
+    fn or(&mut self) -> anyhow::Result<Query> {
+        let mut query = self.and()?;
+        while self.is_keyword("or") {
+            self.position += 1;
+            query = Query::Or(Box::new(query), Box::new(self.and()?));
+        }
+        Ok(query)
+    }
+
+    fn and(&mut self) -> anyhow::Result<Query> {
+        let mut query = self.unary()?;
+        loop {
+            if self.is_keyword("and") {
+                self.position += 1;
+            } else if self.peek().is_none() || self.peek() == Some(&Token::Close) || self.is_keyword("or") {
+                return Ok(query);
+            }
+            query = Query::And(Box::new(query), Box::new(self.unary()?));
+        }
+    }
+
+    fn unary(&mut self) -> anyhow::Result<Query> {
+        let token = self.peek().cloned().ok_or_else(|| anyhow::anyhow!("query ends where a condition was expected"))?;
+        self.position += 1;
+        match token {
+            Token::Word(word) if word.eq_ignore_ascii_case("not") => Ok(Query::Not(Box::new(self.unary()?))),
+            Token::Open => {
+                let query = self.or()?;
+                if self.peek() != Some(&Token::Close) {
+                    return Err(anyhow::anyhow!("missing closing parenthesis in query"));
+                }
+                self.position += 1;
+                Ok(query)
+            }
+            Token::Close => Err(anyhow::anyhow!("unexpected closing parenthesis in query")),
+            Token::Word(word) => parse_condition(&word),
+        }
+    }
+}
+
+/// Operators between a key and its value, longer ones first.
+const OPERATORS: [&str; 8] = ["<=", ">=", "!=", "<", ">", "=", ":", "~"];
+
+fn parse_condition(word: &str) -> anyhow::Result<Query> {
+    match word.to_lowercase().as_str() {
+        "overdue" => return Ok(Query::Overdue),
+        "waiting" => return Ok(Query::Waiting),
+        "recurring" => return Ok(Query::Recurring),
+        "clocked-in" => return Ok(Query::ClockedIn),
+        _ => {}
+    }
+
+    let split = word
+        .find(|c: char| "<>=!:~".contains(c))
+        .ok_or_else(|| anyhow::anyhow!("cannot interpret {word} as a condition; use e.g. tag:work, priority>=high or overdue"))?;
+    let (key, rest) = word.split_at(split);
+    let operator = OPERATORS.iter().find(|operator| rest.starts_with(**operator)).expect("split at an operator");
+    let value = &rest[operator.len()..];
+    if *operator == "!=" {
+        return Ok(Query::Not(Box::new(parse_condition(&format!("{key}={value}"))?)));
+    }
+
+    let comparison = match *operator {
+        "<" => Some(Comparison::Less),
+        "<=" => Some(Comparison::LessOrEqual),
+        ">=" => Some(Comparison::GreaterOrEqual),
+        ">" => Some(Comparison::Greater),
+        _ => None,
+    };
+    let unsupported = || anyhow::anyhow!("{key} does not support the operator {operator}");
+    let key = key.to_lowercase();
+    let date_field = match key.as_str() {
+        "due" => Some(DateField::Due),
+        "created" => Some(DateField::Created),
+        "completed" => Some(DateField::Completed),
+        _ => None,
+    };
+
+    if let Some(field) = date_field {
+        let condition = match (comparison, *operator, value.to_lowercase().as_str()) {
+            (Some(comparison), _, value) => DateCondition::Compare(comparison, value.parse::<DateSpecifier>()?.into()),
+            (None, ":" | "=", "any") => DateCondition::Any,
+            (None, ":" | "=", "none") => DateCondition::None,
+            (None, ":" | "=", value) => DateCondition::Within(value.parse()?),
+            _ => return Err(unsupported()),
+        };
+        return Ok(Query::Date(field, condition));
+    }
+
+    match (key.as_str(), *operator) {
+        ("priority", ":" | "=" | "<" | "<=" | ">=" | ">") => Ok(Query::Priority(comparison.unwrap_or(Comparison::Equal), value.to_lowercase().parse()?)),
+        ("tag", ":" | "=") => Ok(Query::Tag(value.parse()?)),
+        ("category", ":" | "=") => Ok(Query::Category(value.to_lowercase().parse()?)),
+        ("status", ":" | "=") => Ok(Query::Status(match value.to_lowercase().as_str() {
+            "not-started" => StatusFilter::NotStarted,
+            "in-progress" | "started" => StatusFilter::InProgress,
+            "blocked" => StatusFilter::Blocked,
+            "completed" => StatusFilter::Completed,
+            "discarded" => StatusFilter::Discarded,
+            "open" => StatusFilter::Open,
+            "closed" => StatusFilter::Closed,
+            value => {
+                return Err(anyhow::anyhow!(
+                    "{value} is not a status; use not-started, in-progress, blocked, completed, discarded, open or closed"
+                ))
+            }
+        })),
+        ("text", "~") => Ok(Query::Text(RegexBuilder::new(value).case_insensitive(true).build()?)),
+        ("text", ":" | "=") => Ok(Query::Text(RegexBuilder::new(&regex::escape(value)).case_insensitive(true).build()?)),
+        ("id", ":" | "=") => Ok(Query::Ids(
+            value.split(',').map(|id| id.parse().map_err(|_| anyhow::anyhow!("{id} is not a task ID"))).collect::<anyhow::Result<_>>()?,
+        )),
+        ("parent", ":" | "=") => Ok(Query::Parent(value.parse().map_err(|_| anyhow::anyhow!("{value} is not a task ID"))?)),
+        ("priority" | "tag" | "category" | "status" | "text" | "id" | "parent", _) => Err(unsupported()),
+        _ => Err(anyhow::anyhow!("unknown query key {key}")),
+    }
+}
+
+impl FromStr for Query {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parser = Parser { tokens: tokenize(s)?, position: 0 };
+        if parser.tokens.is_empty() {
+            return Err(anyhow::anyhow!("empty query"));
+        }
+        let query = parser.or()?;
+        match parser.peek() {
+            None => Ok(query),
+            Some(Token::Close) => Err(anyhow::anyhow!("unexpected closing parenthesis in query")),
+            Some(token) => Err(anyhow::anyhow!("unexpected {token:?} in query")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_and_match() {
+        let bug = Task::from_strings("fix login", Some("bug"), Some("Crashes on empty password"), Some("2022-01-04"), Some("high"), vec!["work"])
+            .expect("could not create task");
+        let chore = Task::from_strings("water plants", None, None, Some("friday"), Some("low"), vec!["home"]).expect("could not create task");
+        let matches = |query: &str| {
+            let query = Query::from_str(query).expect("could not parse");
+            [&bug, &chore].iter().filter(|task| query.matches(task)).map(|task| task.short.to_string()).collect::<Vec<_>>()
+        };
+
+        assert_eq!(matches("tag:work and not tag:home"), vec!["fix login"]);
+        assert_eq!(matches("category:bug or priority<=low"), vec!["fix login", "water plants"]);
+        assert_eq!(matches("priority>=high status:open"), vec!["fix login"]);
+        assert_eq!(matches("overdue"), vec!["fix login"]);
+        assert_eq!(matches("due<friday"), vec!["fix login"]);
+        assert_eq!(matches("due:this-week and text~\"^water\\s\""), vec!["water plants"]);
+        assert_eq!(matches("text:PASSWORD"), vec!["fix login"]);
+        assert_eq!(matches("created:this-week and not (tag:home or category!=bug)"), vec!["fix login"]);
+        assert!(Query::from_str("(tag:work").is_err());
+        assert!(Query::from_str("tag:work)").is_err());
+        assert!(Query::from_str("colour:red").is_err());
+        assert!(Query::from_str("tag~work").is_err());
+    }
+}
@@ -0,0 +1,18 @@
+use serde::{Deserialize, Serialize};
+
+use super::time::span::Span;
+
+/// A notification due some time before a task's deadline, a zero span being the deadline itself.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct Reminder {
+    pub(crate) before: Span,
+
+    /// Whether the notification went out for the current deadline.
+    pub(crate) fired: bool,
+}
+
+impl Reminder {
+    pub(crate) fn new(before: Span) -> Self {
+        Self { before, fired: false }
+    }
+}
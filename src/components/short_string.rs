@@ -2,6 +2,9 @@ use std::{fmt::Display, str::FromStr};
 
 use serde::{Deserialize, Serialize};
 
+use crate::config::config;
+
+/// Default maximum length of short descriptions, see [`Config`](crate::config::Config).
 pub(crate) const SHORT_STRING_THRESHOLD: usize = 50;
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -11,13 +14,14 @@ impl FromStr for ShortString {
     type Err = anyhow::Error;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let threshold = config().short_string_threshold;
         if s.is_empty() {
             Err(anyhow::anyhow!("short string cannot be empty"))
-        } else if s.chars().count() <= SHORT_STRING_THRESHOLD {
+        } else if s.chars().count() <= threshold {
             Ok(Self(s.to_string()))
         } else {
             Err(anyhow::anyhow!(
-                "string must have fewer than {SHORT_STRING_THRESHOLD} characters"
+                "string must have fewer than {threshold} characters"
             ))
         }
     }
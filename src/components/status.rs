@@ -3,53 +3,44 @@ use std::fmt::Display;
 use super::{
     outcome::Outcome,
     short_string::ShortString,
-    tasks::Tasks,
-    time::{today, DATE_FORMAT},
+    time::moment::Moment,
     TaskId,
 };
-use chrono::NaiveDate;
+use itertools::Itertools;
 use serde::{Deserialize, Serialize};
 
 #[non_exhaustive]
-#[derive(Serialize, Deserialize, Clone)]
+#[derive(Default, Serialize, Deserialize, Clone)]
 pub(crate) enum Status {
+    #[default]
     NotStarted,
-    InProgress { date: NaiveDate },
-    BlockedByTask { date: NaiveDate, id: TaskId },
-    BlockedByOther { date: NaiveDate, reason: ShortString },
-    Completed { date: NaiveDate, outcome: Outcome },
-    Discarded { date: NaiveDate, reason: ShortString },
-}
-
-impl Default for Status {
-    fn default() -> Self {
-        Self::NotStarted
-    }
+    InProgress { date: Moment },
+    /// Waiting for the dependencies which were not completed at the time.
+    BlockedByTask { date: Moment, ids: Vec<TaskId> },
+    BlockedByOther { date: Moment, reason: ShortString },
+    Completed { date: Moment, outcome: Outcome },
+    Discarded { date: Moment, reason: ShortString },
 }
 
 impl Status {
     pub(crate) fn in_progress() -> Self {
-        Self::InProgress { date: today() }
+        Self::InProgress { date: Moment::now() }
     }
 
-    pub(crate) fn blocked_by_task(id: TaskId, tasks: &Tasks) -> anyhow::Result<Self> {
-        if !tasks.has_id(id) {
-            Err(anyhow::anyhow!("blocking ID {id} does not exist"))
-        } else {
-            Ok(Self::BlockedByTask { date: today(), id })
-        }
+    pub(crate) fn blocked_by_tasks(ids: Vec<TaskId>) -> Self {
+        Self::BlockedByTask { date: Moment::now(), ids }
     }
 
     pub(crate) fn blocked_by_other(reason: ShortString) -> Self {
-        Self::BlockedByOther { date: today(), reason }
+        Self::BlockedByOther { date: Moment::now(), reason }
     }
 
-    pub(crate) fn completed(outcome: Outcome, date: NaiveDate) -> Self {
+    pub(crate) fn completed(outcome: Outcome, date: Moment) -> Self {
         Self::Completed { date, outcome }
     }
 
     pub(crate) fn discarded(reason: ShortString) -> Self {
-        Self::Discarded { date: today(), reason }
+        Self::Discarded { date: Moment::now(), reason }
     }
 }
 
@@ -57,11 +48,11 @@ impl Display for Status {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Status::NotStarted => write!(f, "NOT STARTED"),
-            Status::InProgress { date } => write!(f, "{} :: IN PROGRESS", date.format(DATE_FORMAT)),
-            Status::BlockedByTask { date, id } => write!(f, "{} :: BLOCKED ID={id}", date.format(DATE_FORMAT)),
-            Status::BlockedByOther { date, reason } => write!(f, "{} :: BLOCKED {reason}", date.format(DATE_FORMAT)),
-            Status::Completed { date, outcome } => write!(f, "{} :: COMPLETED {outcome}", date.format(DATE_FORMAT)),
-            Status::Discarded { date, reason } => write!(f, "{} :: DISCARDED {reason}", date.format(DATE_FORMAT)),
+            Status::InProgress { date } => write!(f, "{date} :: IN PROGRESS"),
+            Status::BlockedByTask { date, ids } => write!(f, "{date} :: BLOCKED ID={}", ids.iter().join(",")),
+            Status::BlockedByOther { date, reason } => write!(f, "{date} :: BLOCKED {reason}"),
+            Status::Completed { date, outcome } => write!(f, "{date} :: COMPLETED {outcome}"),
+            Status::Discarded { date, reason } => write!(f, "{date} :: DISCARDED {reason}"),
         }?;
         Ok(())
     }
@@ -28,5 +28,5 @@ pub(crate) fn tags_from_comma_separated_string(s: String) -> anyhow::Result<Vec<
         return Ok(Vec::new());
     }
     
-    s.split(',').into_iter().map(Tag::from_str).collect()
+    s.split(',').map(Tag::from_str).collect()
 }
\ No newline at end of file
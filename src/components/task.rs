@@ -1,17 +1,28 @@
-use std::collections::HashSet;
+use std::{cmp::Reverse, collections::{BTreeSet, HashSet}};
 
 use super::{
     category::Category,
+    reminder::Reminder,
+    time_entry::TimeEntry,
     short_string::ShortString,
     status::Status,
-    time::{date_specifier::DateSpecifier, duration::TimeInterval}, priority::Priority, tag::Tag, deadline::Deadline, outcome::Outcome,
+    time::{calendar::calendar, duration::TimeInterval, moment::Moment, now, recurrence::Recurrence, span::Span, today}, priority::Priority, tag::Tag, deadline::Deadline, outcome::Outcome,
+    TaskId,
 };
 use anyhow::Result;
-use chrono::NaiveDate;
+use chrono::{DateTime, Duration, Utc};
 use serde::{Deserialize, Serialize};
+use uuid::Uuid;
 
 #[derive(Serialize, Deserialize, Clone)]
 pub(crate) struct Task {
+    /// Persistent identifier, allocated by [`Tasks`](super::tasks::Tasks) when the task is added.
+    pub(crate) id: TaskId,
+    pub(crate) uuid: Uuid,
+    /// ID of the task this one is a step of, if any.
+    pub(crate) parent: Option<TaskId>,
+    /// IDs of the tasks which have to be completed before this one can go ahead.
+    pub(crate) depends_on: BTreeSet<TaskId>,
     pub(crate) short: ShortString,
     pub(crate) long: Option<String>,
     pub(crate) statuses: Vec<Status>,
@@ -19,6 +30,16 @@ pub(crate) struct Task {
     pub(crate) deadline: Deadline,
     pub(crate) priority: Priority,
     pub(crate) tags: HashSet<Tag>,
+    /// Rule for adding the next occurrence once this one is completed.
+    pub(crate) recurrence: Option<Recurrence>,
+    /// Notifications before the deadline, earliest first, always ending with one at the deadline itself.
+    pub(crate) reminders: Vec<Reminder>,
+    /// Moment before which the task is hidden from pending tasks, if it is snoozed.
+    pub(crate) wait: Option<Moment>,
+    /// Time spent on the task, oldest first, the last entry still running while the task is clocked in.
+    pub(crate) time_log: Vec<TimeEntry>,
+    /// When the task was added, unknown for tasks added before this was recorded.
+    pub(crate) created: Option<Moment>,
 }
 
 impl Task {
@@ -26,18 +47,27 @@ impl Task {
         short: ShortString,
         category: Category,
         long: Option<String>,
-        deadline: Option<DateSpecifier>,
+        deadline: Option<Moment>,
         priority: Priority,
         tags: Vec<Tag>,
     ) -> Result<Self> {
         Ok(Self {
+            id: 0,
+            uuid: Uuid::new_v4(),
+            parent: None,
+            depends_on: BTreeSet::new(),
             short,
             long,
             statuses: vec![Status::default()],
             category,
-            deadline: Deadline(deadline.map(NaiveDate::from)),
+            deadline: Deadline(deadline),
             priority,
             tags: tags.into_iter().collect(),
+            recurrence: None,
+            reminders: vec![Reminder::new(Span::default())],
+            wait: None,
+            time_log: Vec::new(),
+            created: Some(Moment::now()),
         })
     }
 
@@ -53,13 +83,15 @@ impl Task {
         use anyhow::Context;
         use std::str::FromStr;
 
+        use super::time::date_specifier::DateSpecifier;
+
         let short = ShortString::from_str(short).with_context(|| "error creating new task")?;
 
         Self::new(
             short,
             category.map(Category::from_str).transpose()?.unwrap_or_default(),
             long.map(|s| s.to_string()),
-            deadline.map(DateSpecifier::from_str).transpose()?,
+            deadline.map(DateSpecifier::from_str).transpose()?.map(Moment::from),
             priority.map(Priority::from_str).transpose()?.unwrap_or_default(),
             tags.into_iter().map(Tag::from_str).collect::<Result<_, _>>()?
         )
@@ -81,16 +113,53 @@ impl Task {
         self.deadline = Deadline(None);
     }
 
+    /// Adds a status, stopping the clock if it closes the task.
     pub(crate) fn set_status(&mut self, status: Status) {
         self.statuses.push(status);
+        if self.is_closed() {
+            self.clock_out();
+        }
     }
 
     pub(crate) fn set_category(&mut self, category: Category) {
         self.category = category;
     }
 
-    pub(crate) fn set_deadline<ND: Into<NaiveDate>>(&mut self, deadline: ND) {
+    pub(crate) fn set_deadline<M: Into<Moment>>(&mut self, deadline: M) {
         self.deadline = Deadline(Some(deadline.into()));
+        for reminder in &mut self.reminders {
+            reminder.fired = false;
+        }
+    }
+
+    /// Adds reminders the given time before the deadline, skipping those the task already has.
+    pub(crate) fn add_reminders(&mut self, spans: &[Span]) {
+        for span in spans {
+            if !self.reminders.iter().any(|reminder| reminder.before == *span) {
+                self.reminders.push(Reminder::new(*span));
+            }
+        }
+        self.reminders.sort_by_key(|reminder| Reverse(reminder.before));
+    }
+
+    /// Removes all reminders but the one at the deadline.
+    pub(crate) fn discard_reminders(&mut self) {
+        self.reminders.retain(|reminder| reminder.before.is_zero());
+    }
+
+    /// Unfired reminders of an open task with a deadline, with the instants they are due at.
+    pub(crate) fn pending_reminders(&self) -> Vec<(usize, DateTime<Utc>)> {
+        let deadline = match self.deadline.0.filter(|_| !self.is_closed()) {
+            Some(deadline) => deadline.instant(),
+            None => return Vec::new(),
+        };
+        self.reminders
+            .iter()
+            .enumerate()
+            .filter(|(_, reminder)| !reminder.fired)
+            // reminders before the range of dates can never fire
+            .filter_map(|(index, reminder)| deadline.checked_sub_signed(reminder.before.duration()).map(|at| (index, at)))
+            .collect()
     }
 
     pub(crate) fn set_priority(&mut self, priority: Priority) {
@@ -115,6 +184,18 @@ impl Task {
         self.statuses.last().expect("no last status")
     }
 
+    /// Reverts to the status the task had before it was blocked by another task.
+    pub(crate) fn unblock(&mut self) {
+        let previous = self
+            .statuses
+            .iter()
+            .rev()
+            .find(|status| !matches!(status, Status::BlockedByTask { .. }))
+            .cloned()
+            .unwrap_or_default();
+        self.set_status(previous);
+    }
+
     pub(crate) fn start(&mut self) -> anyhow::Result<()> {
         if matches!(self.last_status(), Status::NotStarted) {
             self.set_status(Status::in_progress());
@@ -128,14 +209,103 @@ impl Task {
         matches!(self.last_status(), Status::Completed { .. })
     }
 
-    pub(crate) fn is_completed_in_past(&self, throwback: &TimeInterval) -> bool {
+    /// Whether the task needs no more work, i.e. it was either completed or discarded.
+    pub(crate) fn is_closed(&self) -> bool {
+        matches!(self.last_status(), Status::Completed { .. } | Status::Discarded { .. })
+    }
+
+    /// Whether the task is blocked, either by other tasks or for some other reason.
+    pub(crate) fn is_blocked(&self) -> bool {
+        matches!(self.last_status(), Status::BlockedByTask { .. } | Status::BlockedByOther { .. })
+    }
+
+    pub(crate) fn is_completed_within(&self, interval: &TimeInterval) -> bool {
         if let Status::Completed { date, .. } = self.last_status() {
-            throwback.contains(date)
+            interval.contains(&date.date())
         } else {
             false
         }
     }
 
+    pub(crate) fn is_due_within(&self, interval: &TimeInterval) -> bool {
+        self.deadline.0.is_some_and(|deadline| interval.contains(&deadline.date()))
+    }
+
+    /// Starts recording time spent on the task, starting the task itself if needed.
+    ///
+    /// Only one task of a list should be clocked in at a time, which [`Tasks`](super::tasks::Tasks) takes care of.
+    pub(crate) fn clock_in(&mut self) -> Result<()> {
+        if self.is_closed() {
+            return Err(anyhow::anyhow!("cannot clock in on a closed task"));
+        } else if self.is_clocked_in() {
+            return Err(anyhow::anyhow!("already clocked in on task {}", self.id));
+        }
+        if matches!(self.last_status(), Status::NotStarted) {
+            self.start()?;
+        }
+        self.time_log.push(TimeEntry { start: now(), end: None });
+        Ok(())
+    }
+
+    /// Stops recording time, returning the length of the stretch just ended if the task was clocked in.
+    pub(crate) fn clock_out(&mut self) -> Option<Duration> {
+        let entry = self.time_log.last_mut().filter(|entry| entry.is_running())?;
+        entry.end = Some(now());
+        Some(entry.duration())
+    }
+
+    pub(crate) fn is_clocked_in(&self) -> bool {
+        self.time_log.last().is_some_and(TimeEntry::is_running)
+    }
+
+    /// Records time spent on the task without clocking in, as a stretch ending now.
+    pub(crate) fn log_time(&mut self, span: Span) -> Result<()> {
+        let end = now();
+        let start = end
+            .checked_sub_signed(span.duration())
+            .ok_or_else(|| anyhow::anyhow!("cannot log {span}, which would start before the range of supported dates"))?;
+        self.time_log.push(TimeEntry { start, end: Some(end) });
+        self.time_log.sort_by_key(|entry| (entry.is_running(), entry.start));
+        Ok(())
+    }
+
+    /// Total time spent on the task, including the running clock.
+    pub(crate) fn time_spent(&self) -> Duration {
+        self.time_log.iter().map(TimeEntry::duration).fold(Duration::zero(), |total, duration| total + duration)
+    }
+
+    /// Hides the task from pending tasks until the given moment.
+    pub(crate) fn snooze<M: Into<Moment>>(&mut self, until: M) -> Result<()> {
+        let until = until.into();
+        if self.is_closed() {
+            return Err(anyhow::anyhow!("cannot snooze a closed task"));
+        } else if until.instant() <= now() {
+            return Err(anyhow::anyhow!("cannot snooze a task until {until}, which has already begun"));
+        }
+        self.wait = Some(until);
+        Ok(())
+    }
+
+    /// Checks whether the task is snoozed, i.e. open with a wait date still to come.
+    pub(crate) fn is_waiting(&self) -> bool {
+        !self.is_closed() && self.wait.is_some_and(|wait| wait.instant() > now())
+    }
+
+    /// Checks whether the task is still open past its deadline.
+    pub(crate) fn is_overdue(&self) -> bool {
+        !self.is_closed()
+            && self.deadline.0.is_some_and(|deadline| match deadline {
+                Moment::Date(date) => date < today(),
+                Moment::DateTime(instant) => instant < now(),
+            })
+    }
+
+    /// Number of working days since the deadline of an overdue task.
+    pub(crate) fn working_days_overdue(&self) -> Option<i64> {
+        let deadline = self.deadline.0.filter(|_| self.is_overdue())?;
+        Some(calendar().working_days_between(deadline.date(), today()))
+    }
+
     pub(crate) fn outcome(&self) -> anyhow::Result<Outcome> {
         if let Status::Completed { outcome, .. } = self.last_status() {
             Ok(*outcome)
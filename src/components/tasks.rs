@@ -1,50 +1,364 @@
-use super::{task::Task, TaskId};
-use anyhow::Result;
+use super::{status::Status, task::Task, time::moment::Moment, TaskId};
+use crate::storage::{self, schema};
+use anyhow::{Context, Result};
+use bson::Document;
+use itertools::Itertools;
 use serde::{Deserialize, Serialize};
-use std::path::Path;
+use std::{collections::{HashMap, HashSet}, path::Path};
+use uuid::Uuid;
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Clone)]
 pub(crate) struct Tasks {
     tasks: Vec<Task>,
+
+    /// Tasks moved out of the active list, kept for their history.
+    archived: Vec<Task>,
+
+    /// Next ID to be allocated, never decreases so that IDs are not reused.
+    next_id: TaskId,
 }
 
 impl Tasks {
     pub(crate) fn new() -> Self {
-        Self { tasks: Vec::new() }
+        Self { tasks: Vec::new(), archived: Vec::new(), next_id: 0 }
     }
 
+    /// Checks whether an active or archived task has the given ID.
     pub(crate) fn has_id(&self, id: TaskId) -> bool {
-        id < self.tasks.len()
+        self.get_task(id).is_some() || self.is_archived(id)
     }
 
-    pub(crate) fn add_task(&mut self, task: Task) {
+    pub(crate) fn is_archived(&self, id: TaskId) -> bool {
+        self.archived.iter().any(|task| task.id == id)
+    }
+
+    pub(crate) fn add_task(&mut self, mut task: Task) -> TaskId {
+        let id = self.next_id;
+        self.next_id += 1;
+        task.id = id;
         self.tasks.push(task);
+        id
+    }
+
+    pub(crate) fn get_task(&self, id: TaskId) -> Option<&Task> {
+        self.tasks.iter().find(|task| task.id == id)
+    }
+
+    /// Gets an active or archived task.
+    pub(crate) fn find_task(&self, id: TaskId) -> Option<&Task> {
+        self.tasks.iter().chain(self.archived.iter()).find(|task| task.id == id)
     }
 
     pub(crate) fn get_task_mut(&mut self, id: TaskId) -> Option<&mut Task> {
-        self.tasks.get_mut(id)
+        self.tasks.iter_mut().find(|task| task.id == id)
     }
 
     pub(crate) fn get_task_mut_err(&mut self, id: TaskId) -> anyhow::Result<&mut Task> {
+        if self.is_archived(id) {
+            return Err(anyhow::anyhow!("task {id} is archived, restore it first"));
+        }
         self.get_task_mut(id)
             .ok_or_else(|| anyhow::anyhow!("no task at ID {id}"))
     }
 
+    /// Permanently removes an active or archived task, its subtasks move up to its own parent.
+    ///
+    /// Tasks depending on it stay blocked, see [`Tasks::dangling_blockers`].
+    pub(crate) fn delete_task(&mut self, id: TaskId) -> anyhow::Result<Task> {
+        let task = if let Some(index) = self.tasks.iter().position(|task| task.id == id) {
+            self.tasks.remove(index)
+        } else if let Some(index) = self.archived.iter().position(|task| task.id == id) {
+            self.archived.remove(index)
+        } else {
+            return Err(anyhow::anyhow!("no task at ID {id}"));
+        };
+
+        for other in self.tasks.iter_mut().chain(self.archived.iter_mut()) {
+            if other.parent == Some(id) {
+                other.parent = task.parent;
+            }
+        }
+        Ok(task)
+    }
+
+    pub(crate) fn archive_task(&mut self, id: TaskId) -> anyhow::Result<()> {
+        let index = self
+            .tasks
+            .iter()
+            .position(|task| task.id == id)
+            .ok_or_else(|| anyhow::anyhow!("no active task at ID {id}"))?;
+        let mut task = self.tasks.remove(index);
+        task.clock_out();
+        insert_sorted(&mut self.archived, task);
+        Ok(())
+    }
+
+    pub(crate) fn restore_task(&mut self, id: TaskId) -> anyhow::Result<()> {
+        let index = self
+            .archived
+            .iter()
+            .position(|task| task.id == id)
+            .ok_or_else(|| anyhow::anyhow!("no archived task at ID {id}"))?;
+        let task = self.archived.remove(index);
+        insert_sorted(&mut self.tasks, task);
+        Ok(())
+    }
+
+    /// Makes a task a subtask of another active task, or a top-level task if `parent` is `None`.
+    pub(crate) fn set_parent(&mut self, id: TaskId, parent: Option<TaskId>) -> anyhow::Result<()> {
+        if let Some(parent) = parent {
+            if self.get_task(parent).is_none() {
+                return Err(anyhow::anyhow!("no active task at ID {parent}"));
+            }
+            if parent == id || self.descendants(id).contains(&parent) {
+                return Err(anyhow::anyhow!("task {parent} is a subtask of task {id}, it cannot be its parent"));
+            }
+        }
+        self.get_task_mut_err(id)?.parent = parent;
+        Ok(())
+    }
+
+    /// Direct subtasks of a task, both active and archived.
+    pub(crate) fn children(&self, id: TaskId) -> impl Iterator<Item = &Task> {
+        self.tasks.iter().chain(self.archived.iter()).filter(move |task| task.parent == Some(id))
+    }
+
+    /// IDs of the subtasks of a task, their subtasks and so on.
+    pub(crate) fn descendants(&self, id: TaskId) -> Vec<TaskId> {
+        let mut descendants = Vec::new();
+        let mut pending = vec![id];
+        while let Some(parent) = pending.pop() {
+            for child in self.children(parent) {
+                // guards against cycles in corrupted caches
+                if child.id != id && !descendants.contains(&child.id) {
+                    descendants.push(child.id);
+                    pending.push(child.id);
+                }
+            }
+        }
+        descendants
+    }
+
+    /// IDs of the active subtasks at any depth which are neither completed nor discarded.
+    pub(crate) fn open_descendants(&self, id: TaskId) -> Vec<TaskId> {
+        self.descendants(id)
+            .into_iter()
+            .filter(|id| self.get_task(*id).is_some_and(|task| !task.is_closed()))
+            .collect()
+    }
+
+    /// Number of completed and total direct subtasks, ignoring discarded ones, or `None` if there are none.
+    pub(crate) fn progress(&self, id: TaskId) -> Option<(usize, usize)> {
+        let mut children = self.children(id).peekable();
+        children.peek()?;
+        let (completed, total) = children
+            .filter(|child| !matches!(child.last_status(), Status::Discarded { .. }))
+            .fold((0, 0), |(completed, total), child| (completed + child.is_completed() as usize, total + 1));
+        Some((completed, total))
+    }
+
+    /// Makes a task depend on others, blocking it until all of them are completed.
+    pub(crate) fn add_dependencies(&mut self, id: TaskId, blockers: &[TaskId]) -> anyhow::Result<()> {
+        self.get_task_mut_err(id)?;
+        for blocker in blockers.iter().copied() {
+            if !self.has_id(blocker) {
+                return Err(anyhow::anyhow!("no task at ID {blocker}"));
+            }
+            if blocker == id || self.upstream(blocker).iter().any(|(upstream, _)| *upstream == id) {
+                return Err(anyhow::anyhow!("task {blocker} depends on task {id}, it cannot block it"));
+            }
+            self.get_task_mut_err(id)?.depends_on.insert(blocker);
+        }
+
+        let open = self.open_blockers(id);
+        if !open.is_empty() {
+            self.get_task_mut_err(id)?.set_status(Status::blocked_by_tasks(open));
+        }
+        Ok(())
+    }
+
+    /// Removes dependencies of a task, which is unblocked by [`Tasks::unblock_dependents`] once none are left open.
+    pub(crate) fn remove_dependencies(&mut self, id: TaskId, blockers: &[TaskId]) -> anyhow::Result<()> {
+        let task = self.get_task_mut_err(id)?;
+        for blocker in blockers {
+            if !task.depends_on.remove(blocker) {
+                eprintln!("WARNING: task {id} did not depend on task {blocker}");
+            }
+        }
+        Ok(())
+    }
+
+    /// IDs of the dependencies of a task which have not been completed yet.
+    pub(crate) fn open_blockers(&self, id: TaskId) -> Vec<TaskId> {
+        self.find_task(id)
+            .map(|task| {
+                task.depends_on
+                    .iter()
+                    .copied()
+                    .filter(|blocker| self.find_task(*blocker).is_none_or(|blocker| !blocker.is_completed()))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// IDs of the open tasks which other open tasks depend on.
+    pub(crate) fn blocking(&self) -> HashSet<TaskId> {
+        self.tasks
+            .iter()
+            .filter(|task| !task.is_closed())
+            .flat_map(|task| task.depends_on.iter().copied())
+            .filter(|id| self.get_task(*id).is_some_and(|task| !task.is_closed()))
+            .collect()
+    }
+
+    /// Returns blocked tasks whose dependencies are all completed to their previous status, returning their IDs.
+    pub(crate) fn unblock_dependents(&mut self) -> Vec<TaskId> {
+        let ready = self
+            .tasks
+            .iter()
+            .filter(|task| matches!(task.last_status(), Status::BlockedByTask { .. }))
+            .filter(|task| self.open_blockers(task.id).is_empty())
+            .map(|task| task.id)
+            .collect::<Vec<_>>();
+        for id in &ready {
+            if let Some(task) = self.get_task_mut(*id) {
+                task.unblock();
+            }
+        }
+        ready
+    }
+
+    /// Tasks a task depends on, directly or through other tasks, with their distance to it.
+    pub(crate) fn upstream(&self, id: TaskId) -> Vec<(TaskId, usize)> {
+        self.walk(id, |task| task.depends_on.iter().copied().collect())
+    }
+
+    /// Tasks depending on a task, directly or through other tasks, with their distance to it.
+    pub(crate) fn downstream(&self, id: TaskId) -> Vec<(TaskId, usize)> {
+        self.walk(id, |task| {
+            self.tasks
+                .iter()
+                .chain(self.archived.iter())
+                .filter(|other| other.depends_on.contains(&task.id))
+                .map(|other| other.id)
+                .collect()
+        })
+    }
+
+    /// Visits the tasks reachable from a task depth-first, each of them once.
+    fn walk(&self, id: TaskId, next: impl Fn(&Task) -> Vec<TaskId>) -> Vec<(TaskId, usize)> {
+        let mut seen = HashSet::from([id]);
+        let mut visited = Vec::new();
+        let mut pending = vec![(id, 0)];
+        while let Some((current, distance)) = pending.pop() {
+            if current != id {
+                visited.push((current, distance));
+            }
+            if let Some(task) = self.find_task(current) {
+                for neighbour in next(task).into_iter().rev() {
+                    if seen.insert(neighbour) {
+                        pending.push((neighbour, distance + 1));
+                    }
+                }
+            }
+        }
+        visited
+    }
+
+    /// Adds the next occurrence of a completed recurring task, which takes over its recurrence rule.
+    ///
+    /// Returns the ID of the new task, or `None` if the task does not recur.
+    pub(crate) fn add_next_occurrence(&mut self, id: TaskId) -> anyhow::Result<Option<TaskId>> {
+        let task = self.get_task_mut_err(id)?;
+        let (recurrence, completed) = match (task.recurrence.take(), task.last_status()) {
+            (Some(recurrence), Status::Completed { date, .. }) => (recurrence, date.date()),
+            (recurrence, _) => {
+                task.recurrence = recurrence;
+                return Ok(None);
+            }
+        };
+
+        let mut next = task.clone();
+        next.uuid = Uuid::new_v4();
+        next.statuses = vec![Status::default()];
+        // occurrences are due at the same time of day as the previous one
+        let date = recurrence
+            .next_deadline(task.deadline.0.map(|deadline| deadline.date()), completed)
+            .ok_or_else(|| anyhow::anyhow!("task {id} has no next occurrence within the range of dates"))?;
+        let deadline = match task.deadline.0.and_then(|deadline| deadline.time()) {
+            Some(time) => Moment::at(date, time),
+            None => Moment::Date(date),
+        };
+        next.set_deadline(deadline);
+        next.depends_on.clear();
+        next.wait = None;
+        next.time_log.clear();
+        next.created = Some(Moment::now());
+        next.recurrence = Some(recurrence);
+        Ok(Some(self.add_task(next)))
+    }
+
+    /// The active task which is clocked in, if any.
+    pub(crate) fn clocked_in(&self) -> Option<TaskId> {
+        self.tasks.iter().find(|task| task.is_clocked_in()).map(|task| task.id)
+    }
+
+    /// Finds `(blocked, blocking)` pairs of tasks depending on a task that no longer exists.
+    pub(crate) fn dangling_blockers(&self) -> Vec<(TaskId, TaskId)> {
+        self.tasks
+            .iter()
+            .chain(self.archived.iter())
+            .flat_map(|task| task.depends_on.iter().map(move |blocker| (task.id, *blocker)))
+            .filter(|(_, blocker)| !self.has_id(*blocker))
+            .collect()
+    }
+
+    /// Marks reminders as fired if they fired in `other`, for the same task and deadline.
+    ///
+    /// Reminders are sent outside of the journal, such that restoring an older list must not send them again.
+    pub(crate) fn keep_fired_reminders(&mut self, other: &Tasks) {
+        let fired = other
+            .tasks
+            .iter()
+            .chain(other.archived.iter())
+            .map(|task| (task.uuid, task))
+            .collect::<HashMap<_, _>>();
+        for task in self.tasks.iter_mut().chain(self.archived.iter_mut()) {
+            let Some(other) = fired.get(&task.uuid).filter(|other| other.deadline == task.deadline) else {
+                continue;
+            };
+            for reminder in &mut task.reminders {
+                reminder.fired |= other.reminders.iter().any(|fired| fired.fired && fired.before == reminder.before);
+            }
+        }
+    }
+
     pub(crate) fn num_tasks(&self) -> usize {
         self.tasks.len()
     }
 
+    /// Serializes the task list into a document stamped with the current schema version.
+    pub(crate) fn to_document(&self) -> Result<Document> {
+        let mut document = bson::to_document(self)?;
+        schema::stamp(&mut document);
+        Ok(document)
+    }
+
+    /// Deserializes a task list from a document of any schema version.
+    pub(crate) fn from_document(mut document: Document) -> Result<Self> {
+        schema::migrate(&mut document)?;
+        Ok(bson::from_document(document)?)
+    }
+
     pub(crate) fn save(self, filepath: &Path) -> Result<()> {
-        let bytes = bson::to_vec(&self)?;
-        std::fs::write(filepath, bytes)?;
-        Ok(())
+        storage::write_document(filepath, &self.to_document()?)
     }
 
     pub(crate) fn load(filepath: &Path) -> Result<Self> {
         if filepath.is_file() {
-            let file = std::fs::File::open(filepath)?;
-            let tasks = bson::from_reader::<_, Self>(file)?;
-            Ok(tasks)
+            Self::from_document(storage::read_document(filepath)?).with_context(|| {
+                format!("could not read wwida cache {}, try running `wwida doctor`", filepath.display())
+            })
         } else {
             Ok(Self::new())
         }
@@ -54,16 +368,133 @@ impl Tasks {
         self.tasks.clear();
     }
 
-    pub(crate) fn iter(&self) -> std::slice::Iter<Task> {
+    /// Fixes inconsistencies between tasks, returning a description of each problem found.
+    pub(crate) fn repair(&mut self) -> Vec<String> {
+        let mut problems = Vec::new();
+
+        for (blocked, blocking) in self.dangling_blockers() {
+            let task = self.tasks.iter_mut().chain(self.archived.iter_mut()).find(|task| task.id == blocked);
+            if let Some(task) = task {
+                task.depends_on.remove(&blocking);
+                problems.push(format!("task {blocked}: removed dependency on task {blocking}, which no longer exists"));
+            }
+        }
+        for (blocked, blocking) in self.cyclic_dependencies() {
+            if let Some(task) = self.tasks.iter_mut().chain(self.archived.iter_mut()).find(|task| task.id == blocked) {
+                task.depends_on.remove(&blocking);
+                problems.push(format!("task {blocked}: removed dependency on task {blocking}, which depends on it"));
+            }
+        }
+        for id in self.unblock_dependents() {
+            problems.push(format!("task {id}: unblocked, all of its dependencies are completed"));
+        }
+
+        // only the clock started last keeps running
+        let mut clocks = self.tasks.iter_mut().chain(self.archived.iter_mut()).filter(|task| task.is_clocked_in()).collect::<Vec<_>>();
+        clocks.sort_by_key(|task| task.time_log.last().map(|entry| entry.start));
+        for task in clocks.into_iter().rev().skip(1) {
+            task.clock_out();
+            problems.push(format!("task {}: clocked out, another task was clocked in later", task.id));
+        }
+
+        let ids = self.tasks.iter().chain(self.archived.iter()).map(|task| task.id).collect::<Vec<_>>();
+        for task in self.tasks.iter_mut().chain(self.archived.iter_mut()) {
+            if let Some(parent) = task.parent.filter(|parent| !ids.contains(parent)) {
+                task.parent = None;
+                problems.push(format!("task {}: parent task {parent} no longer exists, made it a top-level task", task.id));
+            }
+        }
+        for id in ids.iter().copied() {
+            if self.is_own_ancestor(id) {
+                if let Some(task) = self.tasks.iter_mut().chain(self.archived.iter_mut()).find(|task| task.id == id) {
+                    task.parent = None;
+                    problems.push(format!("task {id}: is its own ancestor, made it a top-level task"));
+                }
+            }
+        }
+
+        if let Some(duplicate) = ids.iter().duplicates().next() {
+            problems.push(format!("ID {duplicate} is used by several tasks, reassigning IDs of duplicates"));
+            let mut seen = HashSet::new();
+            for task in self.tasks.iter_mut().chain(self.archived.iter_mut()) {
+                if !seen.insert(task.id) {
+                    task.id = self.next_id;
+                    self.next_id += 1;
+                }
+            }
+        }
+
+        if let Some(max_id) = ids.iter().max().filter(|max_id| **max_id >= self.next_id) {
+            problems.push(format!("next ID {} was already in use, moved to {}", self.next_id, max_id + 1));
+            self.next_id = max_id + 1;
+        }
+
+        self.tasks.sort_by_key(|task| task.id);
+        self.archived.sort_by_key(|task| task.id);
+        problems
+    }
+
+    /// Finds `(blocked, blocking)` dependencies, one per cycle, which have to go for the dependencies to be acyclic.
+    fn cyclic_dependencies(&self) -> Vec<(TaskId, TaskId)> {
+        let mut removed: Vec<(TaskId, TaskId)> = Vec::new();
+        for task in self.tasks.iter().chain(self.archived.iter()) {
+            for blocker in task.depends_on.iter().copied() {
+                // walk the dependencies as they are once the edges found so far are removed
+                let mut pending = vec![blocker];
+                let mut seen = HashSet::new();
+                while let Some(current) = pending.pop() {
+                    if current == task.id {
+                        removed.push((task.id, blocker));
+                        break;
+                    }
+                    if let Some(current_task) = self.find_task(current).filter(|_| seen.insert(current)) {
+                        pending.extend(
+                            current_task.depends_on.iter().copied().filter(|next| !removed.contains(&(current, *next))),
+                        );
+                    }
+                }
+            }
+        }
+        removed
+    }
+
+    /// Follows the parents of a task, checking whether they lead back to it.
+    fn is_own_ancestor(&self, id: TaskId) -> bool {
+        let mut seen = HashSet::new();
+        let mut current = id;
+        while let Some(parent) = self.find_task(current).and_then(|task| task.parent) {
+            if parent == id {
+                return true;
+            } else if !seen.insert(parent) {
+                return false;
+            }
+            current = parent;
+        }
+        false
+    }
+
+    pub(crate) fn iter(&self) -> std::slice::Iter<'_, Task> {
         self.tasks.iter()
     }
+
+    pub(crate) fn iter_archived(&self) -> std::slice::Iter<'_, Task> {
+        self.archived.iter()
+    }
+}
+
+/// Inserts a task while keeping the list ordered by ID.
+fn insert_sorted(tasks: &mut Vec<Task>, task: Task) {
+    let index = tasks.partition_point(|other| other.id < task.id);
+    tasks.insert(index, task);
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::format::{print_long, print_short};
+    use crate::format::{print_long, print_short, tree};
 
     use super::*;
+    use crate::components::{outcome::Outcome, time::{now, today}};
+    use chrono::Duration;
 
     fn sample_list() -> Tasks {
         let task_vec = vec![
@@ -81,14 +512,179 @@ mod tests {
             .expect("could not create task"),
             Task::from_strings("eat", Some("message"), None, None, Some("low"), vec!["this-is-a-very-long-tag-1234", "one", "two", "three", "four"]).expect("could not create task"),
         ];
-        Tasks { tasks: task_vec }
+        let mut tasks = Tasks::new();
+        for task in task_vec {
+            tasks.add_task(task);
+        }
+        tasks
     }
 
     #[test]
     fn display() {
-        let list = sample_list();
-        print_short(list.tasks.iter().enumerate());
+        let mut list = sample_list();
+        list.set_parent(1, Some(0)).expect("could not set parent");
+        print_short(tree(list.tasks.iter().collect(), &list).into_iter());
         println!();
-        print_long(list.tasks.iter().enumerate());
+        print_long(tree(list.tasks.iter().collect(), &list).into_iter());
+    }
+
+    #[test]
+    fn subtasks() {
+        let mut list = sample_list();
+        list.set_parent(1, Some(0)).expect("could not set parent");
+        list.set_parent(2, Some(1)).expect("could not set parent");
+        assert!(list.set_parent(0, Some(2)).is_err());
+        assert!(list.set_parent(0, Some(0)).is_err());
+        assert_eq!(list.descendants(0), vec![1, 2]);
+
+        list.set_parent(3, Some(0)).expect("could not set parent");
+        list.get_task_mut_err(3).expect("no task").set_status(Status::completed(Outcome::Success, today().into()));
+        assert_eq!(list.progress(0), Some((1, 2)));
+        assert_eq!(list.open_descendants(0), vec![1, 2]);
+
+        list.delete_task(1).expect("could not delete");
+        assert_eq!(list.get_task(2).expect("no task").parent, Some(0));
+        assert!(list.repair().is_empty());
+    }
+
+    #[test]
+    fn snooze() {
+        let mut list = sample_list();
+        let (past, future) = (Moment::DateTime(now() - Duration::hours(1)), Moment::DateTime(now() + Duration::hours(1)));
+        let task = list.get_task_mut_err(0).expect("no task");
+        assert!(task.snooze(past).is_err());
+        task.snooze(future).expect("could not snooze");
+        assert!(task.is_waiting());
+
+        task.recurrence = Some("daily".parse().expect("invalid recurrence"));
+        task.set_status(Status::completed(Outcome::Success, today().into()));
+        assert!(!task.is_waiting());
+        assert!(list.get_task_mut_err(0).expect("no task").snooze(future).is_err());
+
+        // the next occurrence is not snoozed along
+        let next = list.add_next_occurrence(0).expect("could not add occurrence").expect("task does not recur");
+        assert_eq!(list.get_task(next).expect("no task").wait, None);
+    }
+
+    #[test]
+    fn time_tracking() {
+        let mut list = sample_list();
+        list.get_task_mut_err(0).expect("no task").clock_in().expect("could not clock in");
+        assert!(matches!(list.get_task(0).expect("no task").last_status(), Status::InProgress { .. }));
+        assert_eq!(list.clocked_in(), Some(0));
+
+        let task = list.get_task_mut_err(1).expect("no task");
+        task.log_time("1h30m".parse().expect("invalid span")).expect("could not log time");
+        task.clock_in().expect("could not clock in");
+        assert_eq!(task.time_spent(), chrono::Duration::minutes(90));
+        assert_eq!(list.repair(), vec!["task 0: clocked out, another task was clocked in later".to_string()]);
+        assert_eq!(list.clocked_in(), Some(1));
+
+        list.get_task_mut_err(1).expect("no task").set_status(Status::discarded("no time".parse().expect("invalid reason")));
+        assert_eq!(list.clocked_in(), None);
+    }
+
+    #[test]
+    fn ids_survive_clear() {
+        let mut list = sample_list();
+        list.clear();
+        let id = list.add_task(Task::from_strings("wash up", None, None, None, None, vec![]).expect("could not create task"));
+        assert_eq!(id, 4);
+        assert!(list.has_id(4));
+        assert!(!list.has_id(0));
+    }
+
+    #[test]
+    fn archive_and_restore() {
+        let mut list = sample_list();
+        list.archive_task(1).expect("could not archive");
+        list.archive_task(0).expect("could not archive");
+        assert!(list.has_id(1));
+        assert!(list.get_task_mut_err(1).is_err());
+        assert!(list.archive_task(1).is_err());
+        assert_eq!(list.iter_archived().map(|task| task.id).collect::<Vec<_>>(), vec![0, 1]);
+
+        list.restore_task(1).expect("could not restore");
+        assert_eq!(list.iter().map(|task| task.id).collect::<Vec<_>>(), vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn dependencies() {
+        let mut list = sample_list();
+        list.get_task_mut_err(2).expect("no task").start().expect("could not start");
+        list.add_dependencies(2, &[0, 1]).expect("could not add dependencies");
+        list.add_dependencies(1, &[3]).expect("could not add dependencies");
+        assert!(list.add_dependencies(3, &[2]).is_err());
+        assert!(list.add_dependencies(2, &[2]).is_err());
+        assert_eq!(list.upstream(2), vec![(0, 1), (1, 1), (3, 2)]);
+        assert_eq!(list.downstream(3), vec![(1, 1), (2, 2)]);
+
+        list.get_task_mut_err(0).expect("no task").set_status(Status::completed(Outcome::Success, today().into()));
+        assert!(list.unblock_dependents().is_empty());
+        assert!(matches!(list.get_task(2).expect("no task").last_status(), Status::BlockedByTask { ids, .. } if ids == &vec![0, 1]));
+
+        list.delete_task(1).expect("could not delete");
+        assert_eq!(list.dangling_blockers(), vec![(2, 1)]);
+        assert!(list.unblock_dependents().is_empty());
+        list.remove_dependencies(2, &[1]).expect("could not remove dependency");
+        assert_eq!(list.unblock_dependents(), vec![2]);
+        assert!(matches!(list.get_task(2).expect("no task").last_status(), Status::InProgress { .. }));
+        assert!(list.delete_task(1).is_err());
+
+        list.get_task_mut_err(3).expect("no task").depends_on.insert(7);
+        assert_eq!(list.dangling_blockers(), vec![(3, 7)]);
+    }
+
+    #[test]
+    fn deleted_blocker_is_dangling() {
+        let mut list = sample_list();
+        list.add_dependencies(2, &[0]).expect("could not add dependency");
+        assert!(list.dangling_blockers().is_empty());
+
+        list.delete_task(0).expect("could not delete");
+        assert_eq!(list.dangling_blockers(), vec![(2, 0)]);
+        assert!(list.unblock_dependents().is_empty());
+        assert!(list.delete_task(0).is_err());
+    }
+
+    #[test]
+    fn migrate_single_blocker() {
+        let mut document = sample_list().to_document().expect("could not serialize");
+        document.insert("version", 2);
+        let task = document.get_array_mut("tasks").expect("no tasks")[2].as_document_mut().expect("task is not a document");
+        task.remove("depends_on");
+        let blocked = bson::doc! { "BlockedByTask": { "date": "2022-01-04", "id": 1_i64 } };
+        task.get_array_mut("statuses").expect("no statuses").push(blocked.into());
+
+        let list = Tasks::from_document(document).expect("could not migrate");
+        let task = list.get_task(2).expect("no task");
+        assert_eq!(task.depends_on.iter().copied().collect::<Vec<_>>(), vec![1]);
+        assert!(matches!(task.last_status(), Status::BlockedByTask { ids, .. } if ids == &vec![1]));
+    }
+
+    #[test]
+    fn migrate_unversioned_cache() {
+        let mut document = bson::to_document(&sample_list()).expect("could not serialize");
+        document.remove("next_id");
+        document.remove("archived");
+        for task in document.get_array_mut("tasks").expect("no tasks").iter_mut() {
+            let task = task.as_document_mut().expect("task is not a document");
+            task.remove("id");
+            task.remove("uuid");
+        }
+
+        let list = Tasks::from_document(document).expect("could not deserialize legacy layout");
+        assert_eq!(list.tasks.iter().map(|task| task.id).collect::<Vec<_>>(), vec![0, 1, 2, 3]);
+        assert_eq!(list.next_id, 4);
+        assert_eq!(list.tasks.iter().map(|task| task.uuid).unique().count(), 4);
+    }
+
+    #[test]
+    fn reject_newer_schema() {
+        let mut document = sample_list().to_document().expect("could not serialize");
+        assert_eq!(schema::version(&document).expect("no version"), schema::SCHEMA_VERSION);
+
+        document.insert("version", schema::SCHEMA_VERSION + 1);
+        assert!(Tasks::from_document(document).is_err());
     }
 }
@@ -0,0 +1,259 @@
+use std::{
+    collections::{BTreeSet, HashSet},
+    ops::Bound,
+    path::Path,
+    sync::{Arc, RwLock},
+};
+
+use anyhow::{Context, Result};
+use chrono::{
+    naive::{MAX_DATE, MIN_DATE},
+    Datelike, NaiveDate, Weekday,
+};
+use clap::ValueEnum;
+
+use super::{add_days, moment::Moment};
+use crate::config::{config, Config};
+
+/// What to do with deadlines falling on a non-working day.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub(crate) enum DeadlinePolicy {
+    /// Keep the deadline as given.
+    #[default]
+    Ignore,
+
+    /// Keep the deadline but print a warning.
+    Warn,
+
+    /// Move the deadline to the previous working day.
+    Earlier,
+
+    /// Move the deadline to the next working day.
+    Later,
+}
+
+/// Days on which work happens, the configured working weekdays minus holidays.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Calendar {
+    working_days: Vec<Weekday>,
+    holidays: BTreeSet<NaiveDate>,
+}
+
+impl Default for Calendar {
+    fn default() -> Self {
+        Self {
+            working_days: vec![Weekday::Mon, Weekday::Tue, Weekday::Wed, Weekday::Thu, Weekday::Fri],
+            holidays: BTreeSet::new(),
+        }
+    }
+}
+
+impl Calendar {
+    /// Builds the calendar of a configuration, reading its holiday file if any.
+    pub(crate) fn from_config(config: &Config) -> Result<Self> {
+        let holidays = match &config.holidays {
+            Some(filepath) => read_holidays(filepath)?,
+            None => BTreeSet::new(),
+        };
+        Ok(Self { working_days: config.working_days.clone(), holidays })
+    }
+
+    pub(crate) fn is_working_day(&self, date: NaiveDate) -> bool {
+        self.working_days.contains(&date.weekday()) && !self.holidays.contains(&date)
+    }
+
+    /// Moves a date by a number of working days, backwards if negative.
+    ///
+    /// Non-working days are skipped, such that one working day after a Friday is the next Monday. Fails if no day
+    /// is a working day, or if the resulting date is out of range.
+    pub(crate) fn add_working_days(&self, date: NaiveDate, days: i64) -> Result<NaiveDate> {
+        if self.working_days.is_empty() {
+            return Err(anyhow::anyhow!("cannot count working days, as working-days is empty"));
+        }
+        let out_of_range = || anyhow::anyhow!("{days} working days from {date} is out of the range of supported dates");
+        // each full week has at most as many working days as working weekdays
+        let limit = if days > 0 { MAX_DATE.signed_duration_since(date) } else { date.signed_duration_since(MIN_DATE) };
+        if days.unsigned_abs() / self.working_days_per_week() as u64 * 7 > limit.num_days() as u64 {
+            return Err(out_of_range());
+        }
+        let mut date = date;
+        let mut remaining = days.abs();
+        // skip whole weeks while more than a week's worth remains, taking back the holidays they hold
+        loop {
+            let weeks = (remaining - 1) / self.working_days_per_week();
+            if weeks == 0 {
+                break;
+            }
+            let next = add_days(date, weeks * 7 * days.signum()).ok_or_else(out_of_range)?;
+            let holidays = if days > 0 {
+                self.working_holidays((Bound::Excluded(date), Bound::Included(next)))
+            } else {
+                self.working_holidays((Bound::Included(next), Bound::Excluded(date)))
+            };
+            remaining -= weeks * self.working_days_per_week() - holidays;
+            date = next;
+        }
+        for _ in 0..remaining {
+            date = add_days(date, days.signum()).ok_or_else(out_of_range)?;
+            while !self.is_working_day(date) {
+                date = add_days(date, days.signum()).ok_or_else(out_of_range)?;
+            }
+        }
+        Ok(date)
+    }
+
+    /// Counts the working days after `from`, up to and including `to`.
+    pub(crate) fn working_days_between(&self, from: NaiveDate, to: NaiveDate) -> i64 {
+        if to <= from {
+            return 0;
+        }
+        let days = to.signed_duration_since(from).num_days();
+        let mut count = days / 7 * self.working_days_per_week();
+        let mut weekday = from.weekday();
+        for _ in 0..days % 7 {
+            weekday = weekday.succ();
+            if self.working_days.contains(&weekday) {
+                count += 1;
+            }
+        }
+        count - self.working_holidays((Bound::Excluded(from), Bound::Included(to)))
+    }
+
+    fn working_days_per_week(&self) -> i64 {
+        self.working_days.iter().collect::<HashSet<_>>().len() as i64
+    }
+
+    /// Counts the holidays in a range of dates which fall on working weekdays.
+    fn working_holidays(&self, range: (Bound<NaiveDate>, Bound<NaiveDate>)) -> i64 {
+        self.holidays.range(range).filter(|date| self.working_days.contains(&date.weekday())).count() as i64
+    }
+
+    /// The closest working day in the given direction, the date itself if it is one.
+    ///
+    /// Returns `None` if the range of dates ends before a working day.
+    fn closest_working_day(&self, date: NaiveDate, step: i64) -> Option<NaiveDate> {
+        if self.working_days.is_empty() {
+            return Some(date);
+        }
+        let mut date = date;
+        while !self.is_working_day(date) {
+            date = add_days(date, step)?;
+        }
+        Some(date)
+    }
+}
+
+static CALENDAR: RwLock<Option<Arc<Calendar>>> = RwLock::new(None);
+
+/// Current calendar, Monday to Friday without holidays until [`load`] is called.
+pub(crate) fn calendar() -> Arc<Calendar> {
+    let calendar = CALENDAR.read().expect("calendar lock poisoned").clone();
+    calendar.unwrap_or_default()
+}
+
+/// Builds the calendar from the current configuration, reading its holiday file if any.
+pub(crate) fn load() -> Result<()> {
+    let calendar = Calendar::from_config(&config())?;
+    *CALENDAR.write().expect("calendar lock poisoned") = Some(Arc::new(calendar));
+    Ok(())
+}
+
+fn read_holidays(filepath: &Path) -> Result<BTreeSet<NaiveDate>> {
+    let contents = std::fs::read_to_string(filepath)
+        .with_context(|| format!("could not read holiday file {}", filepath.display()))?;
+    parse_holidays(&contents).with_context(|| format!("could not parse holiday file {}", filepath.display()))
+}
+
+/// Parses holidays from either an iCalendar file or a plain-text list.
+///
+/// Plain-text lists have one `YYYY-MM-DD` date per line, optionally followed by a name; `#` starts a comment.
+/// In iCalendar files, every day an event spans is a holiday.
+fn parse_holidays(contents: &str) -> Result<BTreeSet<NaiveDate>> {
+    let mut holidays = BTreeSet::new();
+    if contents.trim_start().starts_with("BEGIN:VCALENDAR") {
+        let ical_date = |line: &str| {
+            let (_, value) = line.rsplit_once(':').unwrap_or_default();
+            NaiveDate::parse_from_str(value.get(..8).unwrap_or(value), "%Y%m%d")
+                .with_context(|| format!("{line} does not hold a valid date"))
+        };
+        let (mut start, mut end) = (None, None);
+        for line in contents.lines().map(str::trim) {
+            if line.starts_with("DTSTART") {
+                start = Some(ical_date(line)?);
+            } else if line.starts_with("DTEND") {
+                end = Some(ical_date(line)?);
+            } else if line == "END:VEVENT" {
+                if let Some(start) = start.take() {
+                    // the end date of an event is exclusive
+                    let end = end.take().filter(|end| *end > start).unwrap_or_else(|| start.succ());
+                    holidays.extend(start.iter_days().take_while(|date| *date < end));
+                }
+            }
+        }
+    } else {
+        for (number, line) in contents.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            if let Some(date) = line.split_whitespace().next() {
+                let date = NaiveDate::parse_from_str(date, "%Y-%m-%d")
+                    .with_context(|| format!("line {} does not start with a YYYY-MM-DD date", number + 1))?;
+                holidays.insert(date);
+            }
+        }
+    }
+    Ok(holidays)
+}
+
+/// Applies the configured [`DeadlinePolicy`] to a deadline, keeping its time of day if it is moved.
+///
+/// Returns the deadline to use, along with a notice for the user if it is not a working day.
+pub(crate) fn check_deadline(deadline: Moment) -> (Moment, Option<String>) {
+    let calendar = calendar();
+    let date = deadline.date();
+    if calendar.is_working_day(date) {
+        return (deadline, None);
+    }
+    let step = match config().non_working_deadlines {
+        DeadlinePolicy::Ignore => return (deadline, None),
+        DeadlinePolicy::Warn => return (deadline, Some(format!("WARNING: deadline {deadline} is not a working day"))),
+        DeadlinePolicy::Earlier => -1,
+        DeadlinePolicy::Later => 1,
+    };
+    let Some(closest) = calendar.closest_working_day(date, step) else {
+        return (deadline, Some(format!("WARNING: deadline {deadline} is not a working day, and cannot be moved to one")));
+    };
+    let shifted = match deadline.time() {
+        Some(time) => Moment::at(closest, time),
+        None => Moment::Date(closest),
+    };
+    (shifted, Some(format!("Deadline {deadline} is not a working day, moved it to {shifted}.")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn working_days_and_holidays() {
+        let text = "# public holidays\n2021-12-27 Christmas (substitute)\n\n2022-01-03\n";
+        let ical = "BEGIN:VCALENDAR\nBEGIN:VEVENT\nDTSTART;VALUE=DATE:20211227\nDTEND;VALUE=DATE:20211229\n\
+                    SUMMARY:Christmas\nEND:VEVENT\nBEGIN:VEVENT\nDTSTART:20220103T000000Z\nEND:VEVENT\nEND:VCALENDAR\n";
+        let holidays = parse_holidays(text).expect("could not parse text");
+        assert_eq!(holidays.len(), 2);
+        assert_eq!(parse_holidays(ical).expect("could not parse iCalendar").len(), 3);
+        assert!(parse_holidays("27/12/2021").is_err());
+
+        let calendar = Calendar { holidays, ..Calendar::default() };
+        let friday = NaiveDate::from_ymd(2021, 12, 31);
+        assert!(!calendar.is_working_day(NaiveDate::from_ymd(2022, 1, 1)));
+        assert_eq!(calendar.add_working_days(friday, 1).ok(), Some(NaiveDate::from_ymd(2022, 1, 4)));
+        assert_eq!(calendar.add_working_days(NaiveDate::from_ymd(2021, 12, 28), -1).ok(), Some(NaiveDate::from_ymd(2021, 12, 24)));
+        assert_eq!(calendar.working_days_between(NaiveDate::from_ymd(2021, 12, 24), NaiveDate::from_ymd(2022, 1, 5)), 6);
+        assert_eq!(calendar.closest_working_day(NaiveDate::from_ymd(2022, 1, 1), 1), Some(NaiveDate::from_ymd(2022, 1, 4)));
+        let far = calendar.add_working_days(friday, 60_000_000).expect("could not add working days");
+        assert_eq!(calendar.working_days_between(friday, far), 60_000_000);
+        assert_eq!(calendar.add_working_days(far, -60_000_000).ok(), Some(friday));
+        let last = Calendar { holidays: BTreeSet::from([MAX_DATE]), ..Calendar::default() };
+        assert_eq!(last.closest_working_day(MAX_DATE, 1), None);
+        assert!(Calendar { working_days: Vec::new(), ..calendar }.add_working_days(friday, 5).is_err());
+    }
+}
@@ -1,11 +1,12 @@
 use std::str::FromStr;
 
-use chrono::{Datelike, Duration, NaiveDate, Weekday};
+use chrono::{DateTime, Datelike, NaiveDate, NaiveDateTime, NaiveTime, Utc, Weekday};
 
-use super::today;
+use super::{add_days, add_months, calendar::calendar, days_into_week, moment::Moment, to_local, today};
+use crate::config::config;
 
 #[non_exhaustive]
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub(crate) enum DateSpecifier {
     Yesterday,
     Today,
@@ -13,26 +14,205 @@ pub(crate) enum DateSpecifier {
     Last(Weekday),
     This(Weekday),
     Next(Weekday),
+
+    /// The first such day from today on, today included.
+    Weekday(Weekday),
     Date(chrono::NaiveDate),
+
+    /// A time of day on a day given by another specifier, in the configured timezone.
+    At(Box<DateSpecifier>, NaiveTime),
+
+    /// An exact instant, from an ISO 8601 datetime.
+    Instant(DateTime<Utc>),
+
+    /// A number of units from today, negative for the past, e.g. `+3d`, `+5bd` or `in/2/weeks`.
+    Offset(i64, Unit),
+
+    /// The first or last day of a week, month, quarter or year, counted from the current one, e.g. `eom` or
+    /// `start-of-next-month`.
+    Boundary(Boundary, Unit, i64),
+
+    /// A day of a month, this year unless it has already passed, e.g. `31-oct` or `oct 31`.
+    MonthDay(u32, u32),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Unit {
+    Days,
+
+    /// Days of the [`Calendar`](super::calendar::Calendar), skipping weekends and holidays.
+    WorkingDays,
+    Weeks,
+    Months,
+    Quarters,
+    Years,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Boundary {
+    Start,
+    End,
 }
 
-fn parse_weekday(s: &str) -> anyhow::Result<Weekday> {
+pub(crate) fn parse_weekday(s: &str) -> anyhow::Result<Weekday> {
     match s {
-        "monday" => Ok(Weekday::Mon),
-        "tuesday" => Ok(Weekday::Tue),
-        "wednesday" => Ok(Weekday::Wed),
-        "thursday" => Ok(Weekday::Thu),
-        "friday" => Ok(Weekday::Fri),
-        "saturday" => Ok(Weekday::Sat),
-        "sunday" => Ok(Weekday::Sun),
+        "monday" | "mon" => Ok(Weekday::Mon),
+        "tuesday" | "tue" | "tues" => Ok(Weekday::Tue),
+        "wednesday" | "wed" => Ok(Weekday::Wed),
+        "thursday" | "thu" | "thurs" => Ok(Weekday::Thu),
+        "friday" | "fri" => Ok(Weekday::Fri),
+        "saturday" | "sat" => Ok(Weekday::Sat),
+        "sunday" | "sun" => Ok(Weekday::Sun),
         s => Err(anyhow::anyhow!("{s} is not a valid day of the week")),
     }
 }
 
+fn parse_month(s: &str) -> Option<u32> {
+    const MONTHS: [&str; 12] = [
+        "january", "february", "march", "april", "may", "june", "july", "august", "september", "october", "november", "december",
+    ];
+    MONTHS
+        .iter()
+        .position(|month| s == *month || (s.len() >= 3 && month.starts_with(s)))
+        .map(|index| index as u32 + 1)
+}
+
+fn parse_unit(s: &str) -> Option<Unit> {
+    match s {
+        "d" | "day" | "days" => Some(Unit::Days),
+        "bd" | "business-day" | "business-days" | "working-day" | "working-days" => Some(Unit::WorkingDays),
+        "w" | "week" | "weeks" => Some(Unit::Weeks),
+        "m" | "month" | "months" => Some(Unit::Months),
+        "q" | "quarter" | "quarters" => Some(Unit::Quarters),
+        "y" | "year" | "years" => Some(Unit::Years),
+        _ => None,
+    }
+}
+
+/// Parses offsets from today, either `+3d`/`-2w` or `in/10/days`.
+fn parse_offset(s: &str) -> Option<DateSpecifier> {
+    let (sign, rest) = if let Some(rest) = s.strip_prefix('+') {
+        (1, rest)
+    } else if let Some(rest) = s.strip_prefix('-') {
+        (-1, rest)
+    } else if let Some(rest) = s.strip_prefix("in/") {
+        let (amount, unit) = rest.split_once('/')?;
+        return Some(DateSpecifier::Offset(amount.parse().ok()?, parse_unit(unit)?));
+    } else {
+        return None;
+    };
+    let split = rest.find(|c: char| !c.is_ascii_digit())?;
+    let amount: i64 = rest[..split].parse().ok()?;
+    Some(DateSpecifier::Offset(sign * amount, parse_unit(&rest[split..])?))
+}
+
+/// Parses period boundaries such as `end-of-month`, `start-of-next-quarter` and the shorthands `eom`, `soq` etc.
+fn parse_boundary(s: &str) -> Option<DateSpecifier> {
+    let shorthand = |boundary, unit| Some(DateSpecifier::Boundary(boundary, unit, 0));
+    match s {
+        "sow" => return shorthand(Boundary::Start, Unit::Weeks),
+        "eow" => return shorthand(Boundary::End, Unit::Weeks),
+        "som" => return shorthand(Boundary::Start, Unit::Months),
+        "eom" => return shorthand(Boundary::End, Unit::Months),
+        "soq" => return shorthand(Boundary::Start, Unit::Quarters),
+        "eoq" => return shorthand(Boundary::End, Unit::Quarters),
+        "soy" => return shorthand(Boundary::Start, Unit::Years),
+        "eoy" => return shorthand(Boundary::End, Unit::Years),
+        _ => {}
+    }
+
+    let (boundary, rest) = if let Some(rest) = s.strip_prefix("start-of-") {
+        (Boundary::Start, rest)
+    } else {
+        (Boundary::End, s.strip_prefix("end-of-")?)
+    };
+    let (offset, period) = if let Some(period) = rest.strip_prefix("next-") {
+        (1, period)
+    } else if let Some(period) = rest.strip_prefix("last-") {
+        (-1, period)
+    } else {
+        (0, rest.strip_prefix("this-").unwrap_or(rest))
+    };
+    match parse_unit(period)? {
+        Unit::Days | Unit::WorkingDays => None,
+        unit => Some(DateSpecifier::Boundary(boundary, unit, offset)),
+    }
+}
+
+/// Parses days with a month name, such as `31-oct`, `oct 31`, `31 october 2023` or `oct-31-2023`.
+fn parse_month_day(s: &str) -> Option<DateSpecifier> {
+    let parts = s.split(['-', ' ']).filter(|part| !part.is_empty()).collect::<Vec<_>>();
+    let (first, second, year) = match parts.as_slice() {
+        [first, second] => (first, second, None),
+        [first, second, year] => (first, second, Some(year.parse::<i32>().ok()?)),
+        _ => return None,
+    };
+    let (month, day) = match (parse_month(first), parse_month(second)) {
+        (Some(month), None) => (month, second.parse::<u32>().ok()?),
+        (None, Some(month)) => (month, first.parse::<u32>().ok()?),
+        _ => return None,
+    };
+
+    match year {
+        Some(year) => NaiveDate::from_ymd_opt(year, month, day).map(DateSpecifier::Date),
+        // any leap year accepts every valid day of the month
+        None => NaiveDate::from_ymd_opt(2000, month, day).map(|_| DateSpecifier::MonthDay(month, day)),
+    }
+}
+
+/// Parses a time of day, either on a 24-hour clock such as `14:30`, or on a 12-hour one such as `9am` or `2:30pm`.
+fn parse_time(s: &str) -> anyhow::Result<NaiveTime> {
+    let lowercase = s.to_lowercase();
+    let (clock, offset) = if let Some(clock) = lowercase.strip_suffix("am") {
+        (clock, Some(0))
+    } else if let Some(clock) = lowercase.strip_suffix("pm") {
+        (clock, Some(12))
+    } else {
+        (lowercase.as_str(), None)
+    };
+
+    let (hour, minute) = clock.trim().split_once(':').unwrap_or((clock.trim(), "0"));
+    let time = match (str::parse::<u32>(hour), str::parse::<u32>(minute), offset) {
+        (Ok(hour), Ok(minute), Some(offset)) if (1..=12).contains(&hour) => NaiveTime::from_hms_opt(hour % 12 + offset, minute, 0),
+        (Ok(hour), Ok(minute), None) => NaiveTime::from_hms_opt(hour, minute, 0),
+        _ => None,
+    };
+    time.ok_or_else(|| anyhow::anyhow!("{s} is not a valid time of day; use e.g. 14:30, 9am or 2:30pm"))
+}
+
 impl FromStr for DateSpecifier {
     type Err = anyhow::Error;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let spec = Self::parse(s)?;
+        // resolved once here, such that dates out of range are reported rather than panicking later on
+        spec.resolve().map_err(|error| anyhow::anyhow!("cannot interpret {} as a date: {error}", s.trim()))?;
+        Ok(spec)
+    }
+}
+
+impl DateSpecifier {
+    fn parse(s: &str) -> anyhow::Result<Self> {
+        let s = s.trim();
+        if let Ok(instant) = DateTime::parse_from_rfc3339(s) {
+            return Ok(Self::Instant(instant.with_timezone(&Utc)));
+        }
+        let iso_datetime = ["%Y-%m-%dT%H:%M:%S", "%Y-%m-%dT%H:%M"]
+            .iter()
+            .find_map(|format| NaiveDateTime::parse_from_str(s, format).ok());
+        if let Some(datetime) = iso_datetime {
+            return Ok(Self::At(Box::new(Self::Date(datetime.date())), datetime.time()));
+        }
+        if let Ok(date) = NaiveDate::parse_from_str(s, "%Y-%m-%d") {
+            return Ok(Self::Date(date));
+        }
+
+        let lowercase = s.to_lowercase();
+        let s = lowercase.as_str();
+        if let Some(spec) = parse_offset(s).or_else(|| parse_boundary(s)).or_else(|| parse_month_day(s)) {
+            return Ok(spec);
+        }
+
         if s == "yesterday" {
             Ok(Self::Yesterday)
         } else if s == "today" {
@@ -45,35 +225,119 @@ impl FromStr for DateSpecifier {
             parse_weekday(stripped).map(Self::Next)
         } else if let Some(stripped) = s.strip_prefix("last/") {
             parse_weekday(stripped).map(Self::Last)
+        } else if let Ok(day) = parse_weekday(s) {
+            Ok(Self::Weekday(day))
         } else {
-            chrono::NaiveDate::parse_from_str(s, "%d/%m/%Y")
-                .map_err(|_| anyhow::anyhow!("fixed date must be given as e.g. 31/10/2022"))
-                .map(Self::Date)
+            let formats = &config().date_input_formats;
+            if let Some(date) = formats.iter().find_map(|format| chrono::NaiveDate::parse_from_str(s, format).ok()) {
+                Ok(Self::Date(date))
+            } else if let Some((day, time)) = s.split_once('@').or_else(|| s.rsplit_once(' ')) {
+                Ok(Self::At(Box::new(day.trim().parse()?), parse_time(time.trim())?))
+            } else {
+                Err(anyhow::anyhow!(
+                    "cannot interpret {s} as a date; use e.g. today, fri, +3d, eom, 31-oct, 2022-10-31 or one of the formats {}",
+                    formats.join(", ")
+                ))
+            }
         }
     }
 }
 
-impl From<DateSpecifier> for NaiveDate {
-    fn from(spec: DateSpecifier) -> Self {
+impl DateSpecifier {
+    /// The date the specifier refers to, days of a month without a year falling on their first occurrence on or after
+    /// `from` rather than after today.
+    pub(crate) fn resolve_from(&self, from: NaiveDate) -> anyhow::Result<NaiveDate> {
+        match self {
+            DateSpecifier::MonthDay(month, day) => {
+                // the next year which has that day, as leap days can be up to eight years apart
+                (from.year()..=from.year().saturating_add(8))
+                    .filter_map(|year| NaiveDate::from_ymd_opt(year, *month, *day))
+                    .find(|date| *date >= from)
+                    .ok_or_else(out_of_range)
+            }
+            DateSpecifier::At(day, _) => day.resolve_from(from),
+            spec => spec.resolve(),
+        }
+    }
+
+    /// The date the specifier refers to, failing if it is out of the range of dates or cannot be counted.
+    fn resolve(&self) -> anyhow::Result<NaiveDate> {
         let today = today();
 
-        match spec {
+        let date = match self {
             DateSpecifier::This(day) => {
-                let delta = day.num_days_from_monday() as i64 - today.weekday().num_days_from_monday() as i64;
-                today + Duration::days(delta)
+                let delta = days_into_week(*day) - days_into_week(today.weekday());
+                add_days(today, delta)
             }
             DateSpecifier::Next(day) => {
-                let delta = day.num_days_from_monday() as i64 - today.weekday().num_days_from_monday() as i64;
-                today + Duration::days(delta + 7)
+                let delta = days_into_week(*day) - days_into_week(today.weekday());
+                add_days(today, delta + 7)
             },
             DateSpecifier::Last(day) => {
-                let delta = day.num_days_from_monday() as i64 - today.weekday().num_days_from_monday() as i64;
-                today + Duration::days(delta - 7)
+                let delta = days_into_week(*day) - days_into_week(today.weekday());
+                add_days(today, delta - 7)
+            }
+            DateSpecifier::Weekday(day) => {
+                let delta = (day.num_days_from_monday() + 7 - today.weekday().num_days_from_monday()) % 7;
+                add_days(today, delta as i64)
             }
-            DateSpecifier::Date(date) => date,
-            DateSpecifier::Today => today,
-            DateSpecifier::Tomorrow => today.succ(),
-            DateSpecifier::Yesterday => today.pred(),
+            DateSpecifier::Offset(amount, unit) => match unit {
+                Unit::Days => add_days(today, *amount),
+                Unit::WorkingDays => return calendar().add_working_days(today, *amount),
+                Unit::Weeks => amount.checked_mul(7).and_then(|days| add_days(today, days)),
+                Unit::Months => add_months(today, *amount),
+                Unit::Quarters => amount.checked_mul(3).and_then(|months| add_months(today, months)),
+                Unit::Years => amount.checked_mul(12).and_then(|months| add_months(today, months)),
+            },
+            DateSpecifier::Boundary(boundary, unit, offset) => period_boundary(today, *boundary, *unit, *offset),
+            DateSpecifier::MonthDay(..) | DateSpecifier::At(..) => return self.resolve_from(today),
+            DateSpecifier::Date(date) => Some(*date),
+            DateSpecifier::Instant(instant) => Some(to_local(*instant).date()),
+            DateSpecifier::Today => Some(today),
+            DateSpecifier::Tomorrow => today.succ_opt(),
+            DateSpecifier::Yesterday => today.pred_opt(),
+        };
+        date.ok_or_else(out_of_range)
+    }
+}
+
+fn out_of_range() -> anyhow::Error {
+    anyhow::anyhow!("the date is out of the range of supported dates")
+}
+
+/// The first or last day of a week, month, quarter or year, `offset` periods away from the one containing `today`.
+fn period_boundary(today: NaiveDate, boundary: Boundary, unit: Unit, offset: i64) -> Option<NaiveDate> {
+    let (start, length) = match unit {
+        Unit::Days | Unit::WorkingDays | Unit::Weeks => {
+            let start = add_days(today, offset.checked_mul(7)?.checked_sub(days_into_week(today.weekday()))?)?;
+            return match boundary {
+                Boundary::Start => Some(start),
+                Boundary::End => add_days(start, 6),
+            };
+        }
+        Unit::Months => (NaiveDate::from_ymd(today.year(), today.month(), 1), 1),
+        Unit::Quarters => (NaiveDate::from_ymd(today.year(), today.month0() / 3 * 3 + 1, 1), 3),
+        Unit::Years => (NaiveDate::from_ymd(today.year(), 1, 1), 12),
+    };
+    let start = add_months(start, offset.checked_mul(length)?)?;
+    match boundary {
+        Boundary::Start => Some(start),
+        Boundary::End => add_months(start, length)?.pred_opt(),
+    }
+}
+
+impl From<DateSpecifier> for NaiveDate {
+    fn from(spec: DateSpecifier) -> Self {
+        spec.resolve().expect("dates which cannot be resolved are rejected when parsing")
+    }
+}
+
+impl From<DateSpecifier> for Moment {
+    fn from(spec: DateSpecifier) -> Self {
+        match spec {
+            DateSpecifier::At(day, time) => Moment::at(NaiveDate::from(*day), time),
+            DateSpecifier::Instant(instant) => Moment::DateTime(instant),
+            spec => Moment::Date(NaiveDate::from(spec)),
         }
     }
 }
@@ -81,6 +345,7 @@ impl From<DateSpecifier> for NaiveDate {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use chrono::TimeZone;
 
     #[test]
     fn date_specifier_from_str() {
@@ -137,4 +402,57 @@ mod tests {
             NaiveDate::from_isoywd(2022, 1, Weekday::Wed)
         );
     }
+
+    #[test]
+    fn date_specifier_with_time() {
+        let at = |day, hour, minute| DateSpecifier::At(Box::new(day), NaiveTime::from_hms(hour, minute, 0));
+        assert_eq!(at(DateSpecifier::Tomorrow, 14, 30), DateSpecifier::from_str("tomorrow@14:30").expect("could not parse"));
+        assert_eq!(at(DateSpecifier::Weekday(Weekday::Fri), 9, 0), DateSpecifier::from_str("friday 9am").expect("could not parse"));
+        assert_eq!(at(DateSpecifier::Today, 0, 15), DateSpecifier::from_str("today@12:15am").expect("could not parse"));
+        assert_eq!(
+            at(DateSpecifier::Date(NaiveDate::from_ymd(2022, 8, 1)), 9, 0),
+            DateSpecifier::from_str("2022-08-01T09:00").expect("could not parse")
+        );
+        assert_eq!(
+            DateSpecifier::Instant(Utc.ymd(2022, 8, 1).and_hms(7, 0, 0)),
+            DateSpecifier::from_str("2022-08-01T09:00:00+02:00").expect("could not parse")
+        );
+        assert!(DateSpecifier::from_str("friday 13pm").is_err());
+        assert_eq!(at(DateSpecifier::MonthDay(10, 31), 17, 0), DateSpecifier::from_str("Oct 31 5PM").expect("could not parse"));
+
+        assert_eq!(NaiveDate::from(DateSpecifier::Weekday(Weekday::Wed)), today());
+        assert_eq!(NaiveDate::from(DateSpecifier::Weekday(Weekday::Tue)), NaiveDate::from_isoywd(2022, 2, Weekday::Tue));
+        let moment = Moment::from(DateSpecifier::from_str("friday@17:00").expect("could not parse"));
+        assert_eq!((moment.date(), moment.time()), (NaiveDate::from_isoywd(2022, 1, Weekday::Fri), Some(NaiveTime::from_hms(17, 0, 0))));
+    }
+
+    #[test]
+    fn date_expressions() {
+        let date = |s: &str| NaiveDate::from(DateSpecifier::from_str(s).expect("could not parse"));
+        assert_eq!(date("+3d"), NaiveDate::from_ymd(2022, 1, 8));
+        assert_eq!(date("-2W"), NaiveDate::from_ymd(2021, 12, 22));
+        assert_eq!(date("in/10/days"), NaiveDate::from_ymd(2022, 1, 15));
+        assert_eq!(date("+3bd"), NaiveDate::from_ymd(2022, 1, 10));
+        assert_eq!(date("in/5/working-days"), NaiveDate::from_ymd(2022, 1, 12));
+        assert_eq!(date("+1m"), NaiveDate::from_ymd(2022, 2, 5));
+        assert_eq!(date("EOM"), NaiveDate::from_ymd(2022, 1, 31));
+        assert_eq!(date("end-of-next-month"), NaiveDate::from_ymd(2022, 2, 28));
+        assert_eq!(date("start-of-next-month"), NaiveDate::from_ymd(2022, 2, 1));
+        assert_eq!(date("eoq"), NaiveDate::from_ymd(2022, 3, 31));
+        assert_eq!(date("start-of-last-year"), NaiveDate::from_ymd(2021, 1, 1));
+        assert_eq!(date("eow"), NaiveDate::from_ymd(2022, 1, 9));
+        assert_eq!(date("2022-10-31"), NaiveDate::from_ymd(2022, 10, 31));
+        assert_eq!(date("31-oct"), NaiveDate::from_ymd(2022, 10, 31));
+        assert_eq!(date("Jan 1"), NaiveDate::from_ymd(2023, 1, 1));
+        assert_eq!(date("29 february 2024"), NaiveDate::from_ymd(2024, 2, 29));
+        assert_eq!(date("29-feb"), NaiveDate::from_ymd(2024, 2, 29));
+        assert_eq!(date("fri"), NaiveDate::from_ymd(2022, 1, 7));
+        assert_eq!(date("Next/Fri"), NaiveDate::from_ymd(2022, 1, 14));
+        assert!(DateSpecifier::from_str("31-feb").is_err());
+        assert!(DateSpecifier::from_str("+3x").is_err());
+        // beyond the range of dates, rather than panicking
+        for s in ["+99999999d", "+1000000y", "-2000000q", "+9223372036854775807w", "in/999999999/working-days"] {
+            assert!(DateSpecifier::from_str(s).is_err(), "{s}");
+        }
+    }
 }
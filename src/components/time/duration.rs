@@ -1,61 +1,164 @@
 use std::str::FromStr;
 
-use chrono::NaiveDate;
-use serde::{Deserialize, Serialize};
+use chrono::{Datelike, NaiveDate};
 
-use super::today;
+use super::{
+    add_days, add_months,
+    calendar::calendar,
+    date_specifier::{Boundary, DateSpecifier, Unit},
+    today,
+};
 
-#[non_exhaustive]
-#[derive(Debug, Serialize, Deserialize)]
-pub(crate) enum TimeInterval {
-    Days(usize),
-    Weeks(usize),
-    Months(usize),
+/// An inclusive range of days, open-ended if a bound is missing.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct TimeInterval {
+    pub(crate) start: Option<NaiveDate>,
+    pub(crate) end: Option<NaiveDate>,
 }
 
 impl TimeInterval {
-    /// Checks whether a date is in the past throwback interval.
+    /// Checks whether a date lies within the interval.
     ///
     /// ```txt
-    /// false true
-    /// <---- |----------------------->
-    /// ------X--------0---------------
-    ///       tb date  today
+    /// false true               false
+    /// <---- |---------------| ------>
+    /// ------X-------X-------X--------
+    ///       start   date    end
     /// ```
     pub(crate) fn contains(&self, date: &NaiveDate) -> bool {
-        let delta = today() - *date;
+        self.start.is_none_or(|start| start <= *date) && self.end.is_none_or(|end| *date <= end)
+    }
 
-        // future dates are automatically contained
-        if delta.num_days() < 0 {
-            return true;
-        }
+    /// The past number of units up to today, months, quarters and years following the calendar.
+    ///
+    /// Returns `None` if the interval would start before the range of dates.
+    fn past(amount: i64, unit: Unit) -> Option<Self> {
+        let today = today();
+        let amount = amount.checked_neg()?;
+        let start = match unit {
+            Unit::Days => add_days(today, amount),
+            Unit::WorkingDays => calendar().add_working_days(today, amount).ok(),
+            Unit::Weeks => add_days(today, amount.checked_mul(7)?),
+            Unit::Months => add_months(today, amount),
+            Unit::Quarters => add_months(today, amount.checked_mul(3)?),
+            Unit::Years => add_months(today, amount.checked_mul(12)?),
+        }?;
+        Some(Self { start: Some(start), end: Some(today) })
+    }
 
-        match self {
-            TimeInterval::Days(days) => delta.num_days() as usize <= *days,
-            TimeInterval::Weeks(weeks) => delta.num_weeks() as usize <= *weeks,
-            TimeInterval::Months(months) => delta.num_days() as usize <= months * 30,
+    /// A whole week, month, quarter or year, counted from the current one.
+    fn period(unit: Unit, offset: i64) -> Self {
+        Self {
+            start: Some(DateSpecifier::Boundary(Boundary::Start, unit, offset).into()),
+            end: Some(DateSpecifier::Boundary(Boundary::End, unit, offset).into()),
         }
     }
 }
 
+fn parse_date(s: &str) -> anyhow::Result<NaiveDate> {
+    Ok(s.parse::<DateSpecifier>()?.into())
+}
+
+/// Parses a date, days of a month without a year falling on their first occurrence on or after `from`.
+fn parse_date_from(s: &str, from: NaiveDate) -> anyhow::Result<NaiveDate> {
+    s.parse::<DateSpecifier>()?.resolve_from(from)
+}
+
 impl FromStr for TimeInterval {
     type Err = anyhow::Error;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim().to_lowercase();
+        let past = |unit, num: &str| {
+            let num = str::parse::<i64>(num).map_err(|_| anyhow::anyhow!("{num} is not a valid number"))?;
+            if num < 1 {
+                return Err(anyhow::anyhow!("{s} covers no time, the number must be at least 1"));
+            }
+            Self::past(num, unit).ok_or_else(|| anyhow::anyhow!("{s} starts before the range of supported dates"))
+        };
+
         if let Some(num) = s.strip_prefix("days/") {
-            str::parse::<usize>(num)
-                .map(Self::Days)
-                .map_err(|_| anyhow::anyhow!("{num} is not a valid number of days"))
+            past(Unit::Days, num)
         } else if let Some(num) = s.strip_prefix("weeks/") {
-            str::parse::<usize>(num)
-                .map(Self::Weeks)
-                .map_err(|_| anyhow::anyhow!("{num} is not a valid number of weeks"))
+            past(Unit::Weeks, num)
         } else if let Some(num) = s.strip_prefix("months/") {
-            str::parse::<usize>(num)
-                .map(Self::Months)
-                .map_err(|_| anyhow::anyhow!("{num} is not a valid number of months"))
+            past(Unit::Months, num)
+        } else if let Some(num) = s.strip_prefix("quarters/") {
+            past(Unit::Quarters, num)
+        } else if let Some(num) = s.strip_prefix("years/") {
+            past(Unit::Years, num)
+        } else if let Some(date) = s.strip_prefix("since:") {
+            // the last occurrence of a day of a month, up to today
+            let year_ago = add_months(today(), -12).and_then(|date| date.succ_opt()).expect("today is far from the end of time");
+            Ok(Self { start: Some(parse_date_from(date, year_ago)?), end: None })
+        } else if let Some(date) = s.strip_prefix("until:") {
+            Ok(Self { start: None, end: Some(parse_date(date)?) })
+        } else if let Some((start, end)) = s.split_once("..") {
+            // days of a month start in the current year, whether or not they have passed, and end after the start
+            let start = parse_date_from(start, NaiveDate::from_ymd(today().year(), 1, 1))?;
+            let end = parse_date_from(end, start)?;
+            if start > end {
+                return Err(anyhow::anyhow!("range {s} ends before it starts"));
+            }
+            Ok(Self { start: Some(start), end: Some(end) })
+        } else if let Some((offset, unit)) = s.split_once('-').and_then(|(offset, name)| {
+            let offset = match offset {
+                "last" => -1,
+                "this" => 0,
+                "next" => 1,
+                _ => return None,
+            };
+            let unit = match name {
+                "week" => Unit::Weeks,
+                "month" => Unit::Months,
+                "quarter" => Unit::Quarters,
+                "year" => Unit::Years,
+                _ => return None,
+            };
+            Some((offset, unit))
+        }) {
+            Ok(Self::period(unit, offset))
         } else {
-            Err(anyhow::anyhow!("cannot interpret {s} as a time unit; use days/n, weeks/n or months/n"))
+            // any single day, e.g. yesterday or 2022-09-01
+            let date = parse_date(&s).map_err(|_| {
+                anyhow::anyhow!(
+                    "cannot interpret {s} as a time interval; use days/n, weeks/n, months/n, quarters/n, years/n, \
+                     this-week, last-month, since:<date>, until:<date>, <date>..<date> or a single date"
+                )
+            })?;
+            Ok(Self { start: Some(date), end: Some(date) })
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn calendar_intervals() {
+        let interval = |s: &str| TimeInterval::from_str(s).expect("could not parse");
+        let range = |start, end| TimeInterval { start: Some(start), end: Some(end) };
+        let today = today();
+
+        assert_eq!(interval("months/1"), range(NaiveDate::from_ymd(2021, 12, 5), today));
+        assert_eq!(interval("quarters/1"), range(NaiveDate::from_ymd(2021, 10, 5), today));
+        assert!(!interval("days/3").contains(&today.succ()));
+        assert_eq!(interval("last-month"), range(NaiveDate::from_ymd(2021, 12, 1), NaiveDate::from_ymd(2021, 12, 31)));
+        assert_eq!(interval("this-week"), range(NaiveDate::from_ymd(2022, 1, 3), NaiveDate::from_ymd(2022, 1, 9)));
+        assert_eq!(interval("1-sep..15-sep"), range(NaiveDate::from_ymd(2022, 9, 1), NaiveDate::from_ymd(2022, 9, 15)));
+        assert_eq!(interval("yesterday"), range(today.pred(), today.pred()));
+        // days of a month within ranges do not move to their next occurrence once past
+        assert_eq!(interval("1-jan..3-jan"), range(NaiveDate::from_ymd(2022, 1, 1), NaiveDate::from_ymd(2022, 1, 3)));
+        assert_eq!(interval("since:1-sep").start, Some(NaiveDate::from_ymd(2021, 9, 1)));
+        assert_eq!(interval("since:5-jan").start, Some(today));
+        assert_eq!(interval("2021-12-20..3-jan"), range(NaiveDate::from_ymd(2021, 12, 20), NaiveDate::from_ymd(2022, 1, 3)));
+
+        let since = interval("since:2022-01-01");
+        assert!(since.contains(&today.succ()) && !since.contains(&NaiveDate::from_ymd(2021, 12, 31)));
+        assert_eq!(interval("15-dec..15-jan"), range(NaiveDate::from_ymd(2022, 12, 15), NaiveDate::from_ymd(2023, 1, 15)));
+        assert!(TimeInterval::from_str("2022-09-15..2022-09-01").is_err());
+        assert!(TimeInterval::from_str("days/-3").is_err() && TimeInterval::from_str("weeks/0").is_err());
+        assert!(TimeInterval::from_str("days/99999999").is_err() && TimeInterval::from_str("since:+1000000y").is_err());
+    }
+}
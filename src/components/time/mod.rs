@@ -1,12 +1,89 @@
+pub(crate) mod calendar;
 pub(crate) mod date_specifier;
 pub(crate) mod duration;
+pub(crate) mod moment;
+pub(crate) mod recurrence;
+pub(crate) mod span;
 
+use chrono::{DateTime, Datelike, Duration, Local, NaiveDate, NaiveDateTime, TimeZone, Utc, Weekday};
+
+use crate::config::config;
+
+/// Default format used to display dates, see [`Config`](crate::config::Config).
 pub(crate) const DATE_FORMAT: &str = "%a, %0e-%b-%Y";
 
+/// Default format used to display times of day, see [`Config`](crate::config::Config).
+pub(crate) const TIME_FORMAT: &str = "%H:%M";
+
+/// Number of days between the configured start of the week and the given day.
+pub(crate) fn days_into_week(day: Weekday) -> i64 {
+    let week_start = config().week_start;
+    ((day.num_days_from_monday() + 7 - week_start.num_days_from_monday()) % 7) as i64
+}
+
+/// The given day of a month, clamped to the last day of the month, or `None` if the year is out of range.
+pub(crate) fn day_in_month(year: i32, month: u32, day: u32) -> Option<NaiveDate> {
+    let last_day = match month {
+        12 => 31,
+        _ => NaiveDate::from_ymd_opt(year, month + 1, 1)?.pred().day(),
+    };
+    NaiveDate::from_ymd_opt(year, month, day.min(last_day))
+}
+
+/// Moves a date by a number of calendar months, clamping the day to the length of the resulting month.
+///
+/// Returns `None` if the resulting date is out of range.
+pub(crate) fn add_months(date: NaiveDate, months: i64) -> Option<NaiveDate> {
+    let month = (date.year() as i64 * 12 + date.month0() as i64).checked_add(months)?;
+    day_in_month(i32::try_from(month.div_euclid(12)).ok()?, month.rem_euclid(12) as u32 + 1, date.day())
+}
+
+/// Moves a date by a number of days, returning `None` if the resulting date is out of range.
+pub(crate) fn add_days(date: NaiveDate, days: i64) -> Option<NaiveDate> {
+    // any larger number of days leaves the range of dates, and would overflow a `Duration`
+    date.checked_add_signed(Duration::days(i32::try_from(days).ok()?.into()))
+}
+
+pub(crate) fn now() -> DateTime<Utc> {
+    #[cfg(not(test))]
+    return Utc::now();
+
+    #[cfg(test)] // in test configuration, it's always noon on Wed, 05-Jan-2022
+    return Utc.ymd(2022, 1, 5).and_hms(12, 0, 0);
+}
+
+/// Today's date in the configured timezone.
 pub(crate) fn today() -> chrono::NaiveDate {
     #[cfg(not(test))]
-    return chrono::Local::today().naive_local();
+    return to_local(now()).date();
 
     #[cfg(test)] // in test configuration, it's always Wed, 05-Jan-2022
     return chrono::NaiveDate::from_isoywd(2022, 1, chrono::Weekday::Wed);
-}
\ No newline at end of file
+}
+
+/// Converts an instant to the date and time it is in the configured timezone.
+pub(crate) fn to_local(instant: DateTime<Utc>) -> NaiveDateTime {
+    match config().timezone {
+        Some(timezone) => instant.with_timezone(&timezone).naive_local(),
+        None => instant.with_timezone(&Local).naive_local(),
+    }
+}
+
+/// Converts a date and time in the configured timezone to an instant.
+///
+/// Ambiguous times resolve to the earlier instant, times skipped by a daylight saving change to an hour later.
+pub(crate) fn from_local(datetime: NaiveDateTime) -> DateTime<Utc> {
+    fn resolve<Tz: TimeZone>(timezone: &Tz, datetime: NaiveDateTime) -> DateTime<Utc> {
+        timezone
+            .from_local_datetime(&datetime)
+            .earliest()
+            .or_else(|| timezone.from_local_datetime(&(datetime + chrono::Duration::hours(1))).earliest())
+            .map(|instant| instant.with_timezone(&Utc))
+            .unwrap_or_else(|| DateTime::from_utc(datetime, Utc))
+    }
+
+    match config().timezone {
+        Some(timezone) => resolve(&timezone, datetime),
+        None => resolve(&Local, datetime),
+    }
+}
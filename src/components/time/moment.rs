@@ -0,0 +1,106 @@
+use std::{cmp::Ordering, fmt::Display};
+
+use chrono::{DateTime, NaiveDate, NaiveTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::{from_local, now, to_local};
+use crate::config::config;
+
+/// A point in time, either a whole day or an exact instant.
+///
+/// Days are kept as calendar dates, which are the same in every timezone, while instants are stored in UTC and
+/// shown in the configured timezone.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(untagged)]
+pub(crate) enum Moment {
+    Date(NaiveDate),
+    DateTime(DateTime<Utc>),
+}
+
+impl Moment {
+    pub(crate) fn now() -> Self {
+        Self::DateTime(now())
+    }
+
+    /// The given time of day on a date, both in the configured timezone.
+    pub(crate) fn at(date: NaiveDate, time: NaiveTime) -> Self {
+        Self::DateTime(from_local(date.and_time(time)))
+    }
+
+    /// The date in the configured timezone.
+    pub(crate) fn date(&self) -> NaiveDate {
+        match self {
+            Moment::Date(date) => *date,
+            Moment::DateTime(instant) => to_local(*instant).date(),
+        }
+    }
+
+    /// The exact instant, whole days starting at midnight in the configured timezone.
+    pub(crate) fn instant(&self) -> DateTime<Utc> {
+        match self {
+            Moment::Date(date) => from_local(date.and_hms(0, 0, 0)),
+            Moment::DateTime(instant) => *instant,
+        }
+    }
+
+    /// The time of day in the configured timezone, if the moment has one.
+    pub(crate) fn time(&self) -> Option<NaiveTime> {
+        match self {
+            Moment::Date(_) => None,
+            Moment::DateTime(instant) => Some(to_local(*instant).time()),
+        }
+    }
+}
+
+impl From<NaiveDate> for Moment {
+    fn from(date: NaiveDate) -> Self {
+        Self::Date(date)
+    }
+}
+
+impl Ord for Moment {
+    /// Orders by date, whole days coming after all times of that day.
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self, other) {
+            (Moment::DateTime(a), Moment::DateTime(b)) => a.cmp(b),
+            _ => (self.date(), self.time().is_none(), self.time()).cmp(&(other.date(), other.time().is_none(), other.time())),
+        }
+    }
+}
+
+impl PartialOrd for Moment {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Display for Moment {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let config = config();
+        write!(f, "{}", self.date().format(&config.date_output_format))?;
+        if let Some(time) = self.time() {
+            write!(f, " {}", time.format(&config.time_output_format))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn serialization_and_order() {
+        let date = NaiveDate::from_ymd(2022, 1, 5);
+        let morning = Moment::at(date, NaiveTime::from_hms(9, 0, 0));
+        let evening = Moment::at(date, NaiveTime::from_hms(21, 0, 0));
+        assert!(morning < evening && evening < Moment::Date(date) && Moment::Date(date) < Moment::Date(date.succ()));
+        assert_eq!(morning.date(), date);
+        assert_eq!(morning.time(), Some(NaiveTime::from_hms(9, 0, 0)));
+
+        // plain dates written before times of day were supported still read as dates
+        let document = bson::doc! { "date": "2022-01-05", "instant": bson::to_bson(&morning).expect("could not serialize") };
+        assert_eq!(bson::from_bson::<Moment>(document.get("date").unwrap().clone()).expect("not a moment"), Moment::Date(date));
+        assert_eq!(bson::from_bson::<Moment>(document.get("instant").unwrap().clone()).expect("not a moment"), morning);
+    }
+}
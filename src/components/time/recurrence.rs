@@ -0,0 +1,116 @@
+use std::{fmt::Display, str::FromStr};
+
+use chrono::{Datelike, NaiveDate, Weekday};
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+
+use super::{add_days, add_months, date_specifier::parse_weekday, day_in_month};
+
+/// Rule by which a task comes back once it is completed.
+#[non_exhaustive]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) enum Recurrence {
+    Daily,
+
+    /// On each of the given days of the week, in week order.
+    Weekly(Vec<Weekday>),
+
+    /// On the given day of the month, or on the last day of shorter months.
+    Monthly(u32),
+
+    /// The given number of days after the previous occurrence was completed, regardless of its deadline.
+    AfterCompletion(u32),
+}
+
+impl Recurrence {
+    /// Computes the deadline of the occurrence following one with the given deadline, completed on the given date.
+    ///
+    /// Calendar-based rules pick the first matching day after both the deadline and the completion, such that
+    /// occurrences missed while the task was overdue are skipped.
+    ///
+    /// Returns `None` if that deadline is out of the range of dates.
+    pub(crate) fn next_deadline(&self, deadline: Option<NaiveDate>, completed: NaiveDate) -> Option<NaiveDate> {
+        let after = deadline.map_or(completed, |deadline| deadline.max(completed));
+        match self {
+            Recurrence::Daily => after.succ_opt(),
+            Recurrence::Weekly(days) => (1..=7)
+                .filter_map(|delta| add_days(after, delta))
+                .find(|date| days.contains(&date.weekday()))
+                .or_else(|| add_days(after, 7)),
+            Recurrence::Monthly(day) => {
+                let this_month = day_in_month(after.year(), after.month(), *day)?;
+                if this_month > after {
+                    Some(this_month)
+                } else {
+                    let next_month = add_months(after, 1)?;
+                    day_in_month(next_month.year(), next_month.month(), *day)
+                }
+            }
+            Recurrence::AfterCompletion(days) => add_days(completed, *days as i64),
+        }
+    }
+}
+
+impl FromStr for Recurrence {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s == "daily" {
+            Ok(Self::Daily)
+        } else if let Some(days) = s.strip_prefix("weekly/") {
+            let mut days = days.split(',').map(|day| parse_weekday(day.trim())).collect::<anyhow::Result<Vec<_>>>()?;
+            days.sort_unstable_by_key(|day| day.num_days_from_monday());
+            days.dedup();
+            Ok(Self::Weekly(days))
+        } else if let Some(day) = s.strip_prefix("monthly/") {
+            match str::parse::<u32>(day) {
+                Ok(day) if (1..=31).contains(&day) => Ok(Self::Monthly(day)),
+                _ => Err(anyhow::anyhow!("{day} is not a valid day of the month")),
+            }
+        } else if let Some(days) = s.strip_prefix("every/") {
+            match str::parse::<u32>(days) {
+                Ok(days) if days > 0 => Ok(Self::AfterCompletion(days)),
+                _ => Err(anyhow::anyhow!("{days} is not a valid number of days")),
+            }
+        } else {
+            Err(anyhow::anyhow!(
+                "cannot interpret {s} as a recurrence; use daily, weekly/monday,thursday, monthly/n or every/n"
+            ))
+        }
+    }
+}
+
+impl Display for Recurrence {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Recurrence::Daily => write!(f, "DAILY"),
+            Recurrence::Weekly(days) => write!(f, "WEEKLY ON {}", days.iter().map(|day| day.to_string().to_uppercase()).join(", ")),
+            Recurrence::Monthly(day) => write!(f, "MONTHLY ON DAY {day}"),
+            Recurrence::AfterCompletion(days) => write!(f, "{days} DAYS AFTER COMPLETION"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn next_deadline() {
+        let wednesday = NaiveDate::from_ymd(2022, 1, 5);
+        let weekly = Recurrence::from_str("weekly/thursday,monday").expect("could not parse");
+        assert_eq!(weekly, Recurrence::Weekly(vec![Weekday::Mon, Weekday::Thu]));
+        assert_eq!(weekly.next_deadline(Some(wednesday), wednesday), Some(NaiveDate::from_ymd(2022, 1, 6)));
+        assert_eq!(weekly.next_deadline(Some(wednesday.succ()), wednesday), Some(NaiveDate::from_ymd(2022, 1, 10)));
+
+        let monthly = Recurrence::from_str("monthly/31").expect("could not parse");
+        assert_eq!(monthly.next_deadline(Some(NaiveDate::from_ymd(2022, 1, 31)), wednesday), Some(NaiveDate::from_ymd(2022, 2, 28)));
+        assert_eq!(monthly.next_deadline(None, wednesday), Some(NaiveDate::from_ymd(2022, 1, 31)));
+
+        // late completions skip missed occurrences, except for rules relative to the completion
+        let late = NaiveDate::from_ymd(2022, 1, 20);
+        assert_eq!(Recurrence::Daily.next_deadline(Some(wednesday), late), Some(late.succ()));
+        assert_eq!(Recurrence::AfterCompletion(3).next_deadline(Some(wednesday), late), Some(NaiveDate::from_ymd(2022, 1, 23)));
+        assert!(Recurrence::from_str("monthly/32").is_err());
+    }
+}
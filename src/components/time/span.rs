@@ -0,0 +1,109 @@
+use std::{fmt::Display, str::FromStr};
+
+use chrono::{
+    naive::{MAX_DATE, MIN_DATE},
+    Duration,
+};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+const UNITS: [(char, i64); 5] = [('w', 7 * 24 * 3600), ('d', 24 * 3600), ('h', 3600), ('m', 60), ('s', 1)];
+
+/// A length of time such as `1d`, `2h` or `1h30m`, stored as a whole number of seconds.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub(crate) struct Span(i64);
+
+impl Span {
+    pub(crate) fn is_zero(&self) -> bool {
+        self.0 == 0
+    }
+
+    pub(crate) fn duration(&self) -> Duration {
+        Duration::seconds(self.0)
+    }
+
+    /// Span of a duration in whole minutes, for showing time spent without ticking seconds.
+    pub(crate) fn whole_minutes(duration: Duration) -> Self {
+        Self(duration.num_minutes() * 60)
+    }
+}
+
+impl From<Duration> for Span {
+    fn from(duration: Duration) -> Self {
+        Self(duration.num_seconds())
+    }
+}
+
+impl FromStr for Span {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || anyhow::anyhow!("cannot interpret {s} as a length of time; use e.g. 30m, 2h, 1d, 1w or 1h30m");
+        let s = s.trim().to_lowercase();
+        if s == "0" {
+            return Ok(Self(0));
+        } else if s.is_empty() {
+            return Err(invalid());
+        }
+
+        let mut seconds = 0_i64;
+        let mut rest = s.as_str();
+        while !rest.is_empty() {
+            let split = rest.find(|c: char| !c.is_ascii_digit()).ok_or_else(invalid)?;
+            let amount: i64 = rest[..split].parse().map_err(|_| invalid())?;
+            let unit = rest[split..].chars().next().ok_or_else(invalid)?;
+            let (_, length) = UNITS.iter().find(|(name, _)| *name == unit).ok_or_else(invalid)?;
+            seconds = amount.checked_mul(*length).and_then(|span| seconds.checked_add(span)).ok_or_else(invalid)?;
+            rest = &rest[split + unit.len_utf8()..];
+        }
+        // longer spans cannot separate any two dates
+        if seconds > MAX_DATE.and_hms(23, 59, 59).signed_duration_since(MIN_DATE.and_hms(0, 0, 0)).num_seconds() {
+            return Err(anyhow::anyhow!("{s} is longer than the range of supported dates"));
+        }
+        Ok(Self(seconds))
+    }
+}
+
+impl Display for Span {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.0 == 0 {
+            return write!(f, "0m");
+        }
+        let mut rest = self.0;
+        for (name, length) in UNITS {
+            if rest >= length {
+                write!(f, "{}{name}", rest / length)?;
+                rest %= length;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Serialize for Span {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for Span {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_and_display() {
+        let span = |s: &str| Span::from_str(s).expect("could not parse");
+        assert_eq!(span("2h").duration(), Duration::hours(2));
+        assert_eq!(span("1H30m").duration(), Duration::minutes(90));
+        assert_eq!(span("0"), Span::default());
+        assert_eq!(span("36h").to_string(), "1d12h");
+        assert_eq!(span("1w").to_string(), "1w");
+        assert!(Span::from_str("").is_err() && Span::from_str("2").is_err() && Span::from_str("2x").is_err());
+        assert!(Span::from_str("100000000w").is_err() && Span::from_str("10000w").is_ok());
+    }
+}
@@ -0,0 +1,22 @@
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::time::now;
+
+/// A stretch of time spent on a task, still running if it has no end.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct TimeEntry {
+    pub(crate) start: DateTime<Utc>,
+    pub(crate) end: Option<DateTime<Utc>>,
+}
+
+impl TimeEntry {
+    pub(crate) fn is_running(&self) -> bool {
+        self.end.is_none()
+    }
+
+    /// Length of the entry, up to now if it is still running.
+    pub(crate) fn duration(&self) -> Duration {
+        self.end.unwrap_or_else(now) - self.start
+    }
+}
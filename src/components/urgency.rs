@@ -0,0 +1,130 @@
+use std::collections::{BTreeMap, HashSet};
+
+use serde::{Deserialize, Serialize};
+
+use super::{category::Category, task::Task, time::now, TaskId};
+
+/// Days before a deadline from which it starts adding to the urgency of a task.
+const DUE_HORIZON_DAYS: f64 = 14.0;
+
+/// Days of age at which a task gets the full age weight.
+const AGE_HORIZON_DAYS: f64 = 365.0;
+
+/// Weights of the urgency score, higher scores coming first when sorting by urgency.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub(crate) struct UrgencyModel {
+    /// Added per level of priority, from one for very low to five for very high.
+    pub(crate) priority: f64,
+
+    /// Added in full at and past the deadline, rising linearly over the two weeks before it.
+    pub(crate) due: f64,
+
+    /// Added in full for tasks a year old or older, in part for younger ones.
+    pub(crate) age: f64,
+
+    /// Added to blocked tasks, usually negative.
+    pub(crate) blocked: f64,
+
+    /// Added to open tasks other open tasks depend on.
+    pub(crate) blocking: f64,
+
+    /// Added to tasks of a category, e.g. `bug = 3.0`.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty", with = "category_weights")]
+    pub(crate) category: BTreeMap<Category, f64>,
+
+    /// Added to tasks for each of their tags, e.g. `urgent = 5.0` or `someday = -3.0`.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub(crate) tags: BTreeMap<String, f64>,
+}
+
+impl Default for UrgencyModel {
+    fn default() -> Self {
+        Self {
+            priority: 2.0,
+            due: 12.0,
+            age: 2.0,
+            blocked: -5.0,
+            blocking: 8.0,
+            category: BTreeMap::new(),
+            tags: BTreeMap::new(),
+        }
+    }
+}
+
+impl UrgencyModel {
+    /// Urgency score of a task, given the tasks of its list which others depend on, see [`Tasks::blocking`].
+    ///
+    /// [`Tasks::blocking`]: super::tasks::Tasks::blocking
+    pub(crate) fn score(&self, task: &Task, blocking: &HashSet<TaskId>) -> f64 {
+        let now = now();
+        let days_until = |instant: chrono::DateTime<chrono::Utc>| (instant - now).num_seconds() as f64 / 86400.0;
+
+        let mut score = self.priority * task.priority as u8 as f64;
+        if let Some(deadline) = task.deadline.0 {
+            score += self.due * (1.0 - days_until(deadline.instant()) / DUE_HORIZON_DAYS).clamp(0.0, 1.0);
+        }
+        if let Some(created) = task.created {
+            score += self.age * (-days_until(created.instant()) / AGE_HORIZON_DAYS).clamp(0.0, 1.0);
+        }
+        if task.is_blocked() {
+            score += self.blocked;
+        }
+        if blocking.contains(&task.id) {
+            score += self.blocking;
+        }
+        score += self.category.get(&task.category).copied().unwrap_or_default();
+        score += task.tags.iter().filter_map(|tag| self.tags.get(&tag.to_string())).sum::<f64>();
+        score
+    }
+}
+
+/// (De)serializes category weights by the category names used on the command line.
+mod category_weights {
+    use std::collections::BTreeMap;
+
+    use clap::ValueEnum;
+    use serde::{de::Error, Deserialize, Deserializer, Serialize, Serializer};
+
+    use crate::components::category::Category;
+
+    pub(super) fn serialize<S: Serializer>(weights: &BTreeMap<Category, f64>, serializer: S) -> Result<S::Ok, S::Error> {
+        weights
+            .iter()
+            .map(|(category, weight)| {
+                let name = category.to_possible_value().expect("categories have no skipped variants");
+                (name.get_name().to_string(), *weight)
+            })
+            .collect::<BTreeMap<_, _>>()
+            .serialize(serializer)
+    }
+
+    pub(super) fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<BTreeMap<Category, f64>, D::Error> {
+        BTreeMap::<String, f64>::deserialize(deserializer)?
+            .into_iter()
+            .map(|(name, weight)| Ok((Category::from_str(&name, true).map_err(D::Error::custom)?, weight)))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::components::tasks::Tasks;
+
+    use super::*;
+
+    #[test]
+    fn overdue_bug_beats_later_task() {
+        let mut tasks = Tasks::new();
+        tasks.add_task(Task::from_strings("fix crash", Some("bug"), None, Some("2022-01-04"), Some("low"), vec![]).expect("could not create task"));
+        tasks.add_task(Task::from_strings("plan offsite", None, None, Some("2022-02-04"), None, vec!["someday"]).expect("could not create task"));
+        let model: UrgencyModel = toml::from_str("category = { bug = 3.0 }\ntags = { someday = -3.0 }").expect("invalid model");
+        assert_eq!(model.category.get(&Category::Bug), Some(&3.0));
+
+        let score = |id| model.score(tasks.get_task(id).expect("no task"), &tasks.blocking());
+        // 2 * low + full due weight + bug, against 2 * normal minus someday
+        assert_eq!(score(0), 4.0 + 12.0 + 3.0);
+        assert_eq!(score(1), 6.0 - 3.0);
+        assert!(toml::from_str::<UrgencyModel>("category = { chore = 1.0 }").is_err());
+    }
+}
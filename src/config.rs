@@ -0,0 +1,254 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    sync::{Arc, RwLock},
+};
+
+use anyhow::{Context, Result};
+use chrono::Weekday;
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+use toml::value::Table;
+
+use crate::{
+    cli::{report::Report, sort::{Placement, SortBy}},
+    components::{category::Category, priority::Priority, short_string::SHORT_STRING_THRESHOLD, time::{calendar::DeadlinePolicy, DATE_FORMAT, TIME_FORMAT}, urgency::UrgencyModel},
+    format::TaskListFormatter,
+    storage::{sibling_filepath, StorageFormat},
+};
+
+/// Settings read from `~/.config/wwida/config.toml`, optionally overridden per list.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub(crate) struct Config {
+    /// Default output format of `print`.
+    #[serde(with = "value_enum")]
+    pub(crate) format: TaskListFormatter,
+
+    /// Default sorting rule of `print`, e.g. `priority,-deadline,id`.
+    pub(crate) sort: SortBy,
+
+    /// Whether tasks without a deadline come first or last when sorting by deadline.
+    #[serde(with = "value_enum")]
+    pub(crate) no_deadline: Placement,
+
+    /// Default category of new tasks.
+    #[serde(with = "value_enum")]
+    pub(crate) category: Category,
+
+    /// Default priority of new tasks.
+    #[serde(with = "value_enum")]
+    pub(crate) priority: Priority,
+
+    /// Comma-separated list of tags used by `print` when no `--filter` is given.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) filter: Option<String>,
+
+    /// Formats accepted for fixed dates, tried in order.
+    pub(crate) date_input_formats: Vec<String>,
+
+    /// Format used to display dates.
+    pub(crate) date_output_format: String,
+
+    /// Format used to display the time of day of deadlines and statuses which have one.
+    pub(crate) time_output_format: String,
+
+    /// IANA timezone dates and times are read and shown in, e.g. `Europe/London`, defaults to the system's.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) timezone: Option<Tz>,
+
+    /// First day of the week, used by `this/`, `next/` and `last/` dates.
+    pub(crate) week_start: Weekday,
+
+    /// Days of the week on which work happens, used by working-day offsets such as `+5bd` and overdue counts.
+    pub(crate) working_days: Vec<Weekday>,
+
+    /// File listing days off, either iCalendar or one `YYYY-MM-DD` date per line.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) holidays: Option<PathBuf>,
+
+    /// What to do with deadlines falling on a weekend or holiday.
+    #[serde(with = "value_enum")]
+    pub(crate) non_working_deadlines: DeadlinePolicy,
+
+    /// Maximum length of short descriptions.
+    pub(crate) short_string_threshold: usize,
+
+    /// Shell command run for each reminder, with the task in `WWIDA_ID`, `WWIDA_SHORT`, `WWIDA_DEADLINE`,
+    /// `WWIDA_REMINDER` and `WWIDA_MESSAGE`; reminders are printed if it is not set.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) notify_command: Option<String>,
+
+    /// Weights of the urgency score, used by `wwida next` and the `urgency` sort key.
+    pub(crate) urgency: UrgencyModel,
+
+    /// Named reports, run with `wwida report <NAME>`.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub(crate) reports: BTreeMap<String, Report>,
+
+    /// Storage format of new caches whose extension does not select one, existing caches keep theirs.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) storage: Option<StorageFormat>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            format: TaskListFormatter::default(),
+            sort: SortBy::default(),
+            no_deadline: Placement::default(),
+            category: Category::default(),
+            priority: Priority::default(),
+            filter: None,
+            date_input_formats: vec!["%d/%m/%Y".to_string()],
+            date_output_format: DATE_FORMAT.to_string(),
+            time_output_format: TIME_FORMAT.to_string(),
+            timezone: None,
+            week_start: Weekday::Mon,
+            working_days: vec![Weekday::Mon, Weekday::Tue, Weekday::Wed, Weekday::Thu, Weekday::Fri],
+            holidays: None,
+            non_working_deadlines: DeadlinePolicy::default(),
+            short_string_threshold: SHORT_STRING_THRESHOLD,
+            notify_command: None,
+            urgency: UrgencyModel::default(),
+            reports: BTreeMap::new(),
+            storage: None,
+        }
+    }
+}
+
+static CONFIG: RwLock<Option<Arc<Config>>> = RwLock::new(None);
+
+/// Current configuration, the compiled-in defaults until [`load`] is called.
+pub(crate) fn config() -> Arc<Config> {
+    let config = CONFIG.read().expect("config lock poisoned").clone();
+    config.unwrap_or_default()
+}
+
+/// Loads the global configuration, overridden by that of a list if given.
+///
+/// Command-line arguments are parsed before the list is known, so settings used while parsing, such as
+/// `short-string-threshold` and `date-input-formats`, only take effect from the global configuration.
+pub(crate) fn load(list: Option<&Path>) -> Result<()> {
+    let mut tables = Vec::new();
+    if let Some(filepath) = global_filepath() {
+        tables.push(read_table(&filepath)?);
+    }
+    if let Some(list) = list {
+        tables.push(read_table(&list_filepath(list))?);
+    }
+    let config = from_tables(tables)?;
+    *CONFIG.write().expect("config lock poisoned") = Some(Arc::new(config));
+    crate::components::time::calendar::load()
+}
+
+/// Path of the global configuration, honouring `XDG_CONFIG_HOME`.
+pub(crate) fn global_filepath() -> Option<PathBuf> {
+    let directory = match std::env::var_os("XDG_CONFIG_HOME") {
+        Some(directory) if !directory.is_empty() => PathBuf::from(directory),
+        _ => home::home_dir()?.join(".config"),
+    };
+    Some(directory.join("wwida").join("config.toml"))
+}
+
+/// Path of the configuration overriding the global one for a single list.
+pub(crate) fn list_filepath(cache: &Path) -> PathBuf {
+    sibling_filepath(cache, ".config.toml")
+}
+
+/// Reads a configuration file as a raw table, a missing file being an empty table.
+pub(crate) fn read_table(filepath: &Path) -> Result<Table> {
+    if !filepath.is_file() {
+        return Ok(Table::new());
+    }
+    let contents = std::fs::read_to_string(filepath)?;
+    toml::from_str(&contents).with_context(|| format!("could not parse config file {}", filepath.display()))
+}
+
+pub(crate) fn write_table(filepath: &Path, table: &Table) -> Result<()> {
+    if let Some(parent) = filepath.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    // serialized as a value, which puts plain keys before tables such as `reports`
+    std::fs::write(filepath, toml::to_string(&toml::Value::Table(table.clone()))?)?;
+    Ok(())
+}
+
+/// Builds a configuration from tables, later tables overriding earlier ones key by key.
+///
+/// Tables within the tables, such as `reports`, are merged the same way, such that a list can add a report without
+/// hiding the global ones.
+pub(crate) fn from_tables(tables: Vec<Table>) -> Result<Config> {
+    let mut merged = Table::new();
+    for table in tables {
+        for (key, value) in table {
+            match (merged.get_mut(&key), value) {
+                (Some(toml::Value::Table(merged)), toml::Value::Table(table)) => merged.extend(table),
+                (_, value) => {
+                    merged.insert(key, value);
+                }
+            }
+        }
+    }
+    Ok(toml::Value::Table(merged).try_into()?)
+}
+
+/// (De)serializes command-line enums by the same names they have on the command line.
+pub(crate) mod value_enum {
+    use clap::ValueEnum;
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    pub(crate) fn serialize<T: ValueEnum, S: Serializer>(value: &T, serializer: S) -> Result<S::Ok, S::Error> {
+        let value = value.to_possible_value().expect("config enums have no skipped variants");
+        serializer.serialize_str(value.get_name())
+    }
+
+    pub(crate) fn deserialize<'de, T: ValueEnum, D: Deserializer<'de>>(deserializer: D) -> Result<T, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        T::from_str(&name, true).map_err(D::Error::custom)
+    }
+
+    /// The same for optional values, which are left out when missing.
+    pub(crate) mod option {
+        use clap::ValueEnum;
+        use serde::{Deserialize, Deserializer, Serializer};
+
+        pub(crate) fn serialize<T: ValueEnum, S: Serializer>(value: &Option<T>, serializer: S) -> Result<S::Ok, S::Error> {
+            match value {
+                Some(value) => super::serialize(value, serializer),
+                None => serializer.serialize_none(),
+            }
+        }
+
+        pub(crate) fn deserialize<'de, T: ValueEnum, D: Deserializer<'de>>(deserializer: D) -> Result<Option<T>, D::Error> {
+            match Option::<String>::deserialize(deserializer)? {
+                Some(name) => T::from_str(&name, true).map(Some).map_err(serde::de::Error::custom),
+                None => Ok(None),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cli::sort::SortKey;
+
+    use super::*;
+
+    #[test]
+    fn later_tables_override() {
+        let global: Table = toml::from_str("category = \"bug\"\nsort = \"deadline\"").expect("invalid toml");
+        let list: Table = toml::from_str("sort = \"id\"\nweek-start = \"sunday\"").expect("invalid toml");
+        let config = from_tables(vec![global, list]).expect("invalid config");
+        assert_eq!(config.category, Category::Bug);
+        assert_eq!(config.sort, SortBy::by(SortKey::Id));
+        assert_eq!(config.week_start, Weekday::Sun);
+        assert_eq!(config.short_string_threshold, SHORT_STRING_THRESHOLD);
+    }
+
+    #[test]
+    fn reject_unknown_keys() {
+        let table: Table = toml::from_str("colour = \"red\"").expect("invalid toml");
+        assert!(from_tables(vec![table]).is_err());
+    }
+}
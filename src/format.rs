@@ -1,23 +1,169 @@
-use clap::clap_derive::ValueEnum;
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use chrono::{Datelike, Duration};
+use clap::{clap_derive::ValueEnum, ValueEnum as _};
 use itertools::Itertools;
 
-use crate::components::{short_string::SHORT_STRING_THRESHOLD, task::Task, time::DATE_FORMAT, TaskId};
+use crate::{
+    components::{
+        category::Category,
+        short_string::SHORT_STRING_THRESHOLD,
+        status::Status,
+        task::Task,
+        tasks::Tasks,
+        time::{days_into_week, span::Span, today},
+        TaskId,
+    },
+    config::config,
+};
 
 struct TickTock(bool);
 
-#[derive(Debug, Clone, ValueEnum)]
+#[derive(Debug, Clone, Default, ValueEnum)]
 pub(crate) enum TaskListFormatter {
+    #[default]
     Short,
     Long,
 }
 
+/// A task as it appears in a printed list.
+pub(crate) struct Row<'t> {
+    pub(crate) task: &'t Task,
+
+    /// Number of printed ancestors, i.e. how far the task is indented.
+    pub(crate) depth: usize,
+
+    /// Completed and total number of subtasks, for tasks which have any.
+    pub(crate) progress: Option<(usize, usize)>,
+
+    /// Urgency score of the task.
+    pub(crate) urgency: f64,
+}
+
+impl<'t> Row<'t> {
+    /// Builds the row of a task, given the tasks of its list which others depend on, see [`Tasks::blocking`].
+    pub(crate) fn new(task: &'t Task, depth: usize, context: &Tasks, blocking: &HashSet<TaskId>) -> Self {
+        Self { task, depth, progress: context.progress(task.id), urgency: config().urgency.score(task, blocking) }
+    }
+}
+
+/// Field printed tasks can be grouped by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub(crate) enum GroupBy {
+    Category,
+    Tag,
+    Status,
+    Priority,
+    /// Deadline buckets: overdue, today, this week, later and no deadline.
+    Due,
+}
+
+impl GroupBy {
+    /// Groups a task belongs to, as their position among the groups and their title.
+    ///
+    /// Tasks with several tags belong to the group of each of them.
+    fn groups(self, task: &Task) -> Vec<(usize, String)> {
+        match self {
+            GroupBy::Category => {
+                let position = Category::value_variants().iter().position(|category| *category == task.category).unwrap_or_default();
+                vec![(position, task.category.to_string())]
+            }
+            GroupBy::Tag if task.tags.is_empty() => vec![(1, "NO TAGS".to_string())],
+            GroupBy::Tag => task.tags.iter().map(|tag| (0, tag.to_string())).collect(),
+            GroupBy::Status => {
+                let group = match task.last_status() {
+                    Status::NotStarted => (0, "NOT STARTED"),
+                    Status::InProgress { .. } => (1, "IN PROGRESS"),
+                    Status::BlockedByTask { .. } | Status::BlockedByOther { .. } => (2, "BLOCKED"),
+                    Status::Completed { .. } => (3, "COMPLETED"),
+                    Status::Discarded { .. } => (4, "DISCARDED"),
+                };
+                vec![(group.0, group.1.to_string())]
+            }
+            GroupBy::Priority => {
+                let name = task.priority.to_possible_value().expect("priorities have no skipped variants");
+                vec![(5 - task.priority as usize, name.get_name().replace('-', " ").to_uppercase())]
+            }
+            GroupBy::Due => {
+                let today = today();
+                let end_of_week = today + Duration::days(6 - days_into_week(today.weekday()));
+                let group = match task.deadline.0.map(|deadline| deadline.date()) {
+                    _ if task.is_overdue() => (0, "OVERDUE"),
+                    // closed tasks are never overdue
+                    Some(date) if date < today => (1, "PAST"),
+                    Some(date) if date == today => (2, "TODAY"),
+                    Some(date) if date <= end_of_week => (3, "THIS WEEK"),
+                    Some(_) => (4, "LATER"),
+                    None => (5, "NO DEADLINE"),
+                };
+                vec![(group.0, group.1.to_string())]
+            }
+        }
+    }
+}
+
+/// Splits tasks into titled groups, keeping their order within each group.
+fn group(tasks: Vec<&Task>, group_by: GroupBy) -> Vec<(String, Vec<&Task>)> {
+    let mut groups: BTreeMap<(usize, String), Vec<&Task>> = BTreeMap::new();
+    for task in tasks {
+        for key in group_by.groups(task) {
+            groups.entry(key).or_default().push(task);
+        }
+    }
+    groups.into_iter().map(|((_, title), tasks)| (title, tasks)).collect()
+}
+
 impl TaskListFormatter {
-    pub(crate) fn print(&self, tasks: Vec<(TaskId, &Task)>) {
+    /// Prints tasks in the given order, with subtasks nested below their parent.
+    ///
+    /// Grouped tasks are printed group by group, each under a header with its number of tasks.
+    pub(crate) fn print(&self, tasks: Vec<&Task>, context: &Tasks, group_by: Option<GroupBy>) {
+        let Some(group_by) = group_by else {
+            return self.print_rows(tree(tasks, context));
+        };
+        for (title, tasks) in group(tasks, group_by) {
+            print_header(&format!("{title} ({})", tasks.len()));
+            self.print_rows(tree(tasks, context));
+            println!();
+        }
+    }
+
+    pub(crate) fn print_rows(&self, rows: Vec<Row>) {
         match self {
-            TaskListFormatter::Short => print_short(tasks.into_iter()),
-            TaskListFormatter::Long => print_long(tasks.into_iter()),
+            TaskListFormatter::Short => print_short(rows.into_iter()),
+            TaskListFormatter::Long => print_long(rows.into_iter()),
+        }
+    }
+}
+
+/// Moves subtasks right below their parent, keeping the order among siblings.
+///
+/// Subtasks whose parent is not being printed, e.g. because it was filtered out, are shown at the top level.
+pub(crate) fn tree<'t>(tasks: Vec<&'t Task>, context: &Tasks) -> Vec<Row<'t>> {
+    let printed: HashSet<TaskId> = tasks.iter().map(|task| task.id).collect();
+    let mut children: HashMap<TaskId, Vec<&Task>> = HashMap::new();
+    let mut roots = Vec::new();
+    for task in tasks {
+        match task.parent.filter(|parent| printed.contains(parent)) {
+            Some(parent) => children.entry(parent).or_default().push(task),
+            None => roots.push(task),
+        }
+    }
+
+    let blocking = context.blocking();
+    let mut rows = Vec::new();
+    let mut stack = roots.into_iter().rev().map(|task| (task, 0)).collect::<Vec<_>>();
+    while let Some((task, depth)) = stack.pop() {
+        if let Some(subtasks) = children.remove(&task.id) {
+            stack.extend(subtasks.into_iter().rev().map(|subtask| (subtask, depth + 1)));
         }
+        rows.push(Row::new(task, depth, context, &blocking));
     }
+
+    // tasks caught in a cycle of parents never become reachable from the top level
+    let unreachable = children.into_values().flatten().sorted_by_key(|task| task.id);
+    rows.extend(unreachable.map(|task| Row::new(task, 0, context, &blocking)));
+    rows
 }
 
 impl Iterator for TickTock {
@@ -29,15 +175,12 @@ impl Iterator for TickTock {
     }
 }
 
-impl Default for TaskListFormatter {
-    fn default() -> Self {
-        Self::Short
-    }
-}
-
-pub(crate) fn print_short<'t>(tasks: impl Iterator<Item = (TaskId, &'t Task)>) {
-    for ((id, task), dash_clock) in tasks.zip(TickTock(false).into_iter()) {
-        let short = format!("{} ", task.short);
+pub(crate) fn print_short<'t>(rows: impl Iterator<Item = Row<'t>>) {
+    for (Row { task, depth, progress, .. }, dash_clock) in rows.zip(TickTock(false)) {
+        let id = task.id;
+        let indent = if depth > 0 { format!("{}└ ", "  ".repeat(depth - 1)) } else { String::new() };
+        let progress = progress.map(|(completed, total)| format!(" ({completed}/{total} done)")).unwrap_or_default();
+        let short = format!("{indent}{}{progress} ", task.short);
 
         if dash_clock {
             print!("[ {:^7} ] {short:─<52}", id);
@@ -45,47 +188,84 @@ pub(crate) fn print_short<'t>(tasks: impl Iterator<Item = (TaskId, &'t Task)>) {
             print!("[ {:^7} ] {short:╌<52}", id);
         }
 
-        if let Some(deadline) = task.deadline.0 {
-            print!(" [ DUE {} ]", deadline.format(DATE_FORMAT));
-        } else {
-            print!(" [ {:^20} ]", "NO DEADLINE");
-        }
+        // wide enough for deadlines with a time of day, with the default formats
+        let deadline = task.deadline.0.map(|deadline| format!("DUE {deadline}")).unwrap_or_else(|| "NO DEADLINE".to_string());
+        print!(" [ {deadline:^26} ]");
         print!("[ {:<5} ]", task.priority.as_symbol());
 
         println!("[ {:^50} ]", task.last_status().to_string())
     }
 }
 
-pub(crate) fn print_long<'t>(tasks: impl Iterator<Item = (TaskId, &'t Task)>) {
-    for (id, task) in tasks {
-        let id_str = format!("ID {id}");
+pub(crate) fn print_long<'t>(rows: impl Iterator<Item = Row<'t>>) {
+    for Row { task, depth, progress, urgency } in rows {
+        let margin = "    ".repeat(depth);
+        let id_str = format!("ID {}", task.id);
         let sep = std::iter::once('┌')
-            .chain(std::iter::repeat('─').take(id_str.len() + 1))
+            .chain(std::iter::repeat_n('─', id_str.len() + 1))
             .join("");
-        println!(" {}\n{}", id_str, sep);
-        println!("│ SHORT    :: {}", task.short);
+        println!("{margin} {}\n{margin}{}", id_str, sep);
+        println!("{margin}│ UUID     :: {}", task.uuid);
+        println!("{margin}│ SHORT    :: {}", task.short);
         if let Some(long) = &task.long {
-            print_split_string("│ LONG     :: ", long, SHORT_STRING_THRESHOLD);
+            print_split_string(&margin, "│ LONG     :: ", long, SHORT_STRING_THRESHOLD);
+        }
+        if let Some(parent) = task.parent {
+            println!("{margin}│ PARENT   :: {parent}");
+        }
+        if !task.depends_on.is_empty() {
+            println!("{margin}│ DEPENDS  :: {}", task.depends_on.iter().join(", "));
         }
-        println!("│ CATEGORY :: {}", task.category);
+        println!("{margin}│ CATEGORY :: {}", task.category);
         if let Some(deadline) = task.deadline.0 {
-            println!("│ DEADLINE :: {}", deadline.format(DATE_FORMAT));
+            match task.working_days_overdue() {
+                Some(0) => println!("{margin}│ DEADLINE :: {deadline} (OVERDUE)"),
+                Some(1) => println!("{margin}│ DEADLINE :: {deadline} (OVERDUE BY 1 WORKING DAY)"),
+                Some(days) => println!("{margin}│ DEADLINE :: {deadline} (OVERDUE BY {days} WORKING DAYS)"),
+                None => println!("{margin}│ DEADLINE :: {deadline}"),
+            }
+        }
+        if let Some(wait) = task.wait.filter(|_| task.is_waiting()) {
+            println!("{margin}│ WAIT     :: {wait}");
         }
         println!(
-            "│ STATUS   :: {}",
+            "{margin}│ STATUS   :: {}",
             task.statuses.last().expect("task should have a last status")
         );
-        println!("│ PRIORITY :: {}", task.priority.as_symbol());
+        if let Some((completed, total)) = progress {
+            println!("{margin}│ PROGRESS :: {completed}/{total} done");
+        }
+        println!("{margin}│ PRIORITY :: {}", task.priority.as_symbol());
+        println!("{margin}│ URGENCY  :: {urgency:.1}");
+        if let Some(recurrence) = &task.recurrence {
+            println!("{margin}│ RECURS   :: {recurrence}");
+        }
+        if !task.time_log.is_empty() {
+            let running = if task.is_clocked_in() { " (CLOCKED IN)" } else { "" };
+            println!("{margin}│ TIME     :: {}{running}", Span::whole_minutes(task.time_spent()));
+        }
+        let reminders = task.reminders.iter().filter(|reminder| !reminder.before.is_zero()).map(|reminder| reminder.before).join(", ");
+        if !reminders.is_empty() {
+            println!("{margin}│ REMINDS  :: {reminders} BEFORE");
+        }
         if !task.tags.is_empty() {
-            print_split_string("│ TAGS     :: ", &task.tags.iter().join(", "), SHORT_STRING_THRESHOLD);
+            print_split_string(&margin, "│ TAGS     :: ", &task.tags.iter().join(", "), SHORT_STRING_THRESHOLD);
         }
         println!();
     }
 }
 
-fn print_split_string(indent: &str, s: &str, threshold: usize) {
-    print!("{indent}");
-    let indent = " ".repeat(indent.len() - 3);
+/// Prints a section title, e.g. above the tasks of one of several lists.
+pub(crate) fn print_header(title: &str) {
+    let sep = std::iter::once('┌')
+        .chain(std::iter::repeat_n('─', title.chars().count() + 1))
+        .join("");
+    println!(" {title}\n{sep}");
+}
+
+fn print_split_string(margin: &str, indent: &str, s: &str, threshold: usize) {
+    print!("{margin}{indent}");
+    let indent = " ".repeat(indent.chars().count() - 1);
     let mut counter = threshold;
     for word in s.split_ascii_whitespace() {
         if counter > word.len() {
@@ -93,8 +273,30 @@ fn print_split_string(indent: &str, s: &str, threshold: usize) {
             print!("{word} ");
         } else {  // TODO: fix edge case of words >= 50 chars
             counter = threshold - word.len() - 1;
-            print!("\n│{indent}{word} ");
+            print!("\n{margin}│{indent}{word} ");
         }
     }
     println!();
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn group_by_deadline_and_tag() {
+        let mut list = Tasks::new();
+        for (deadline, tags) in [(Some("2022-01-20"), vec!["work", "home"]), (None, vec![]), (Some("2022-01-08"), vec!["work"]), (Some("2022-01-04"), vec![]), (Some("2022-01-05"), vec![])] {
+            list.add_task(Task::from_strings("task", None, None, deadline, None, tags).expect("could not create task"));
+        }
+        let titles = |group_by| {
+            group(list.iter().collect(), group_by)
+                .into_iter()
+                .map(|(title, tasks)| format!("{title} {}", tasks.iter().map(|task| task.id).join(",")))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(titles(GroupBy::Due), vec!["OVERDUE 3", "TODAY 4", "THIS WEEK 2", "LATER 0", "NO DEADLINE 1"]);
+        assert_eq!(titles(GroupBy::Tag), vec!["home 0", "work 0,2", "NO TAGS 1,3,4"]);
+        assert_eq!(titles(GroupBy::Priority), vec!["NORMAL 0,1,2,3,4"]);
+    }
+}
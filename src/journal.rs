@@ -0,0 +1,187 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use bson::Document;
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+
+use crate::{components::tasks::Tasks, storage};
+
+/// Maximum number of operations that can be undone.
+pub(crate) const JOURNAL_LENGTH: usize = 50;
+
+/// A mutating operation, together with the state of the task list just before it was applied.
+///
+/// Snapshots are kept as versioned documents, so that they are migrated like the cache itself when restored.
+#[derive(Serialize, Deserialize, Clone)]
+struct Entry {
+    timestamp: NaiveDateTime,
+    command: String,
+    snapshot: Document,
+
+    /// Set for commands which also changed another list, and thus cannot be undone from this one.
+    #[serde(default)]
+    across_lists: bool,
+}
+
+/// Operation journal kept next to the cache file, used to undo and redo commands.
+#[derive(Serialize, Deserialize, Default, Clone)]
+pub(crate) struct Journal {
+    undo: Vec<Entry>,
+    redo: Vec<Entry>,
+
+    #[serde(skip)]
+    modified: bool,
+}
+
+impl Journal {
+    pub(crate) fn filepath(cache: &Path) -> PathBuf {
+        storage::sibling_filepath(cache, ".journal")
+    }
+
+    pub(crate) fn load(filepath: &Path) -> Result<Self> {
+        if filepath.is_file() {
+            let file = std::fs::File::open(filepath)?;
+            Ok(bson::from_reader(file)?)
+        } else {
+            Ok(Self::default())
+        }
+    }
+
+    pub(crate) fn save(&self, filepath: &Path) -> Result<()> {
+        if self.modified {
+            storage::write_atomic(filepath, &bson::to_vec(self)?)?;
+        }
+        Ok(())
+    }
+
+    /// Records an applied command, given the task list as it was before the command ran.
+    pub(crate) fn record(&mut self, command: String, snapshot: &Tasks) -> Result<()> {
+        let snapshot = snapshot.to_document()?;
+        self.push(Entry { timestamp: now(), command, snapshot, across_lists: false });
+        Ok(())
+    }
+
+    /// Records a command which also changed another list, such as moving tasks between lists.
+    ///
+    /// It is never undone, and so also keeps earlier commands from being undone, as their snapshots would bring back
+    /// tasks which now live in the other list.
+    pub(crate) fn record_across_lists(&mut self, command: String) {
+        self.push(Entry { timestamp: now(), command, snapshot: Document::new(), across_lists: true });
+    }
+
+    fn push(&mut self, entry: Entry) {
+        self.undo.push(entry);
+        if self.undo.len() > JOURNAL_LENGTH {
+            self.undo.remove(0);
+        }
+        self.redo.clear();
+        self.modified = true;
+    }
+
+    /// Puts the journal back as it was before a command whose changes could not be saved.
+    pub(crate) fn revert_to(&mut self, original: Journal) {
+        *self = Self { modified: true, ..original };
+    }
+
+    pub(crate) fn undo(&mut self, tasks: &mut Tasks) -> Result<()> {
+        if let Some(entry) = self.undo.last().filter(|entry| entry.across_lists) {
+            return Err(anyhow::anyhow!(
+                "cannot undo `{}`, which also changed another list, move the tasks back instead",
+                entry.command
+            ));
+        }
+        let entry = self.undo.pop().ok_or_else(|| anyhow::anyhow!("nothing to undo"))?;
+        println!("Undid `{}`.", entry.command);
+        self.redo.push(Self::swap(entry, tasks)?);
+        self.modified = true;
+        Ok(())
+    }
+
+    pub(crate) fn redo(&mut self, tasks: &mut Tasks) -> Result<()> {
+        let entry = self.redo.pop().ok_or_else(|| anyhow::anyhow!("nothing to redo"))?;
+        println!("Redid `{}`.", entry.command);
+        self.undo.push(Self::swap(entry, tasks)?);
+        self.modified = true;
+        Ok(())
+    }
+
+    /// Replaces the task list with the entry's snapshot and returns an entry holding the replaced list.
+    fn swap(entry: Entry, tasks: &mut Tasks) -> Result<Entry> {
+        let Entry { command, snapshot, .. } = entry;
+        let replaced = std::mem::replace(tasks, Tasks::from_document(snapshot)?);
+        tasks.keep_fired_reminders(&replaced);
+        Ok(Entry { timestamp: now(), command, snapshot: replaced.to_document()?, across_lists: false })
+    }
+
+    /// Prints the last `num` operations, most recent first.
+    pub(crate) fn print_history(&self, num: usize) {
+        let undone = self.redo.iter().map(|entry| ("UNDONE", entry));
+        let applied = self.undo.iter().rev().map(|entry| ("", entry));
+        for (state, entry) in undone.chain(applied).take(num) {
+            println!("{} {state:<6} {}", entry.timestamp.format("%Y-%m-%d %H:%M:%S"), entry.command);
+        }
+    }
+}
+
+fn now() -> NaiveDateTime {
+    chrono::Local::now().naive_local()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::components::task::Task;
+
+    use super::*;
+
+    fn add(tasks: &mut Tasks, journal: &mut Journal, short: &str) {
+        let snapshot = tasks.clone();
+        tasks.add_task(Task::from_strings(short, None, None, None, None, vec![]).expect("could not create task"));
+        journal.record(format!("add {short}"), &snapshot).expect("could not record");
+    }
+
+    #[test]
+    fn undo_redo() {
+        let mut tasks = Tasks::new();
+        let mut journal = Journal::default();
+        add(&mut tasks, &mut journal, "first");
+        add(&mut tasks, &mut journal, "second");
+
+        journal.undo(&mut tasks).expect("could not undo");
+        journal.undo(&mut tasks).expect("could not undo");
+        assert_eq!(tasks.num_tasks(), 0);
+        assert!(journal.undo(&mut tasks).is_err());
+
+        journal.redo(&mut tasks).expect("could not redo");
+        assert_eq!(tasks.num_tasks(), 1);
+
+        add(&mut tasks, &mut journal, "third");
+        assert!(journal.redo(&mut tasks).is_err());
+        assert_eq!(journal.undo.len(), 2);
+    }
+
+    #[test]
+    fn moves_are_not_undone() {
+        let mut tasks = Tasks::new();
+        let mut journal = Journal::default();
+        add(&mut tasks, &mut journal, "first");
+        journal.record_across_lists("move 0 --to work".to_string());
+
+        assert!(journal.undo(&mut tasks).is_err());
+        assert_eq!(journal.undo.len(), 2);
+    }
+
+    #[test]
+    fn undo_keeps_fired_reminders() {
+        let mut tasks = Tasks::new();
+        let mut journal = Journal::default();
+        tasks.add_task(Task::from_strings("call back", None, None, Some("2022-01-05"), None, vec![]).expect("could not create task"));
+        add(&mut tasks, &mut journal, "second");
+
+        // as sent by `wwida remind --due`, which is not journaled
+        tasks.get_task_mut(0).expect("no task").reminders[0].fired = true;
+        journal.undo(&mut tasks).expect("could not undo");
+        assert_eq!(tasks.num_tasks(), 1);
+        assert!(tasks.get_task(0).expect("no task").reminders[0].fired);
+    }
+}
@@ -2,9 +2,18 @@ use clap::Parser;
 
 pub(crate) mod cli;
 pub(crate) mod components;
+pub(crate) mod config;
 pub(crate) mod format;
+pub(crate) mod journal;
+pub(crate) mod storage;
 
 fn main() {
+    // loaded before parsing, as some arguments are validated against it; errors are left for `Cli::run` to report, as
+    // it loads the configuration again
+    let _ = config::load(None);
     let cli = cli::Cli::parse();
-    cli.run().unwrap();
+    if let Err(error) = cli.run() {
+        eprintln!("Error: {error:?}");
+        std::process::exit(1);
+    }
 }
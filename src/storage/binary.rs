@@ -0,0 +1,19 @@
+use std::{fs::File, path::Path};
+
+use anyhow::Result;
+use bson::Document;
+
+use super::{write_atomic, Backend};
+
+/// Compact BSON file, the original cache format.
+pub(crate) struct BsonBackend;
+
+impl Backend for BsonBackend {
+    fn read(&self, filepath: &Path) -> Result<Document> {
+        Ok(Document::from_reader(File::open(filepath)?)?)
+    }
+
+    fn write(&self, filepath: &Path, document: &Document) -> Result<()> {
+        write_atomic(filepath, &bson::to_vec(document)?)
+    }
+}
@@ -0,0 +1,22 @@
+use std::{fs::File, io::BufReader, path::Path};
+
+use anyhow::Result;
+use bson::Document;
+
+use super::{write_atomic, Backend};
+
+/// Pretty-printed JSON file, which is human-readable and diffs well under version control.
+pub(crate) struct JsonBackend;
+
+impl Backend for JsonBackend {
+    fn read(&self, filepath: &Path) -> Result<Document> {
+        let value: serde_json::Value = serde_json::from_reader(BufReader::new(File::open(filepath)?))?;
+        Ok(bson::to_document(&value)?)
+    }
+
+    fn write(&self, filepath: &Path, document: &Document) -> Result<()> {
+        let mut bytes = serde_json::to_vec_pretty(document)?;
+        bytes.push(b'\n');
+        write_atomic(filepath, &bytes)
+    }
+}
@@ -0,0 +1,196 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+
+use super::{sibling_filepath, StorageFormat};
+
+/// Name of the list stored in the main cache file.
+pub(crate) const DEFAULT_LIST: &str = "default";
+
+/// File names of project lists, which are picked up in the directory they are in and all directories below.
+const PROJECT_FILENAMES: [&str; 3] = [".wwida", ".wwida.json", ".wwida.sqlite"];
+
+/// Files kept next to a cache, which belong to it and must follow it around.
+const COMPANION_SUFFIXES: [&str; 4] = [".journal", ".backups", ".lock", ".config.toml"];
+
+/// Named task lists, stored in a directory next to the main cache and sharing its storage format.
+pub(crate) struct Lists {
+    default: PathBuf,
+}
+
+impl Lists {
+    pub(crate) fn new(default: PathBuf) -> Self {
+        Self { default }
+    }
+
+    fn directory(&self) -> PathBuf {
+        sibling_filepath(&self.default, ".lists")
+    }
+
+    /// Extension of the main cache, including the dot, which is given to all other lists too.
+    fn extension(&self) -> String {
+        self.default
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map(|extension| format!(".{extension}"))
+            .unwrap_or_default()
+    }
+
+    /// Path of a list's cache, whether or not the list exists.
+    pub(crate) fn filepath(&self, name: &str) -> PathBuf {
+        if name == DEFAULT_LIST {
+            self.default.clone()
+        } else {
+            self.directory().join(format!("{name}{}", self.extension()))
+        }
+    }
+
+    /// Path of a list's cache, failing if the list has not been created.
+    pub(crate) fn existing_filepath(&self, name: &str) -> Result<PathBuf> {
+        let filepath = self.filepath(name);
+        if name == DEFAULT_LIST || filepath.is_file() {
+            Ok(filepath)
+        } else {
+            Err(anyhow::anyhow!("no list named {name}, create it with `wwida list create {name}`"))
+        }
+    }
+
+    /// Names of all lists, starting with the default one.
+    pub(crate) fn names(&self) -> Result<Vec<String>> {
+        let mut names = Vec::new();
+        if self.directory().is_dir() {
+            let extension = self.extension();
+            for entry in std::fs::read_dir(self.directory())? {
+                let filename = entry?.file_name();
+                let name = filename.to_str().and_then(|filename| filename.strip_suffix(&extension));
+                if let Some(name) = name.filter(|name| validate_name(name).is_ok()) {
+                    names.push(name.to_string());
+                }
+            }
+        }
+        names.sort_unstable();
+        names.insert(0, DEFAULT_LIST.to_string());
+        Ok(names)
+    }
+
+    /// Checks that a name is valid and not yet taken, returning the path the list would be stored at.
+    pub(crate) fn new_filepath(&self, name: &str) -> Result<PathBuf> {
+        validate_name(name)?;
+        let filepath = self.filepath(name);
+        if filepath.exists() {
+            return Err(anyhow::anyhow!("list {name} already exists"));
+        }
+        std::fs::create_dir_all(self.directory())?;
+        Ok(filepath)
+    }
+
+    pub(crate) fn rename(&self, name: &str, new_name: &str) -> Result<()> {
+        if name == DEFAULT_LIST {
+            return Err(anyhow::anyhow!("the default list cannot be renamed"));
+        }
+        let filepath = self.existing_filepath(name)?;
+        let new_filepath = self.new_filepath(new_name)?;
+        std::fs::rename(&filepath, &new_filepath)?;
+        for suffix in COMPANION_SUFFIXES {
+            move_if_exists(&sibling_filepath(&filepath, suffix), &sibling_filepath(&new_filepath, suffix))?;
+        }
+        Ok(())
+    }
+
+    pub(crate) fn delete(&self, name: &str) -> Result<()> {
+        if name == DEFAULT_LIST {
+            return Err(anyhow::anyhow!("the default list cannot be deleted"));
+        }
+        let filepath = self.existing_filepath(name)?;
+        std::fs::remove_file(&filepath)?;
+        for suffix in COMPANION_SUFFIXES {
+            let companion = sibling_filepath(&filepath, suffix);
+            if companion.is_dir() {
+                std::fs::remove_dir_all(companion)?;
+            } else if companion.is_file() {
+                std::fs::remove_file(companion)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Looks for a project list in the directory and then in each of its ancestors, like git does for `.git`.
+pub(crate) fn find_project_list(directory: &Path) -> Option<PathBuf> {
+    directory.ancestors().find_map(|ancestor| {
+        PROJECT_FILENAMES.iter().map(|filename| ancestor.join(filename)).find(|filepath| filepath.is_file())
+    })
+}
+
+/// Path of a new project list in the given directory.
+pub(crate) fn project_list_filepath(directory: &Path, format: StorageFormat) -> PathBuf {
+    match format {
+        StorageFormat::Bson => directory.join(PROJECT_FILENAMES[0]),
+        StorageFormat::Json => directory.join(PROJECT_FILENAMES[1]),
+        StorageFormat::Sqlite => directory.join(PROJECT_FILENAMES[2]),
+    }
+}
+
+fn validate_name(name: &str) -> Result<()> {
+    if name.is_empty() || !name.chars().all(|c| c.is_alphanumeric() || c == '-' || c == '_') {
+        Err(anyhow::anyhow!("list name {name} must be alphanumeric, '-' or '_'"))
+    } else if name == DEFAULT_LIST {
+        Err(anyhow::anyhow!("{DEFAULT_LIST} is reserved for the main list"))
+    } else {
+        Ok(())
+    }
+}
+
+fn move_if_exists(from: &Path, to: &Path) -> Result<()> {
+    if from.exists() {
+        std::fs::rename(from, to)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn find_project_list_in_ancestors() {
+        let root = std::env::temp_dir().join(format!("wwida-project-{}", std::process::id()));
+        let nested = root.join("src").join("components");
+        std::fs::create_dir_all(&nested).expect("could not create test directory");
+        assert_eq!(find_project_list(&nested), None);
+
+        let project = project_list_filepath(&root, StorageFormat::Json);
+        std::fs::write(&project, "{}").expect("could not create project list");
+        assert_eq!(find_project_list(&nested), Some(project.clone()));
+        assert_eq!(find_project_list(&root), Some(project));
+
+        std::fs::remove_dir_all(root).expect("could not remove test directory");
+    }
+
+    #[test]
+    fn companions_follow_their_list() {
+        let directory = std::env::temp_dir().join(format!("wwida-lists-{}", std::process::id()));
+        std::fs::create_dir_all(&directory).expect("could not create test directory");
+        let lists = Lists::new(directory.join("cache"));
+        let filepath = lists.new_filepath("foo").expect("could not pick path");
+        std::fs::write(&filepath, "").expect("could not create list");
+        for suffix in [".journal", ".lock", ".config.toml"] {
+            std::fs::write(sibling_filepath(&filepath, suffix), "").expect("could not create companion");
+        }
+        std::fs::create_dir_all(sibling_filepath(&filepath, ".backups")).expect("could not create backups");
+
+        lists.rename("foo", "bar").expect("could not rename");
+        let renamed = lists.filepath("bar");
+        for suffix in COMPANION_SUFFIXES {
+            assert!(!sibling_filepath(&filepath, suffix).exists(), "{suffix}");
+            assert!(sibling_filepath(&renamed, suffix).exists(), "{suffix}");
+        }
+        assert!(lists.rename("bar", "default").is_err() && lists.rename("default", "baz").is_err());
+
+        lists.delete("bar").expect("could not delete");
+        assert!(std::fs::read_dir(lists.directory()).expect("could not read lists").next().is_none());
+        assert!(lists.delete("bar").is_err());
+
+        std::fs::remove_dir_all(directory).expect("could not remove test directory");
+    }
+}
@@ -0,0 +1,250 @@
+use std::{
+    fs::File,
+    io::{Read, Write},
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+use bson::Document;
+use clap::ValueEnum;
+use fs2::FileExt;
+use serde::{Deserialize, Serialize};
+
+use crate::config::config;
+
+use self::{binary::BsonBackend, json::JsonBackend, sqlite::SqliteBackend};
+
+pub(crate) mod binary;
+pub(crate) mod json;
+pub(crate) mod lists;
+pub(crate) mod schema;
+pub(crate) mod sqlite;
+
+/// Header of SQLite database files.
+const SQLITE_HEADER: &[u8] = b"SQLite format 3\0";
+
+/// Number of backups kept next to each cache file.
+pub(crate) const BACKUP_COUNT: usize = 10;
+
+const BACKUP_TIMESTAMP_FORMAT: &str = "%Y%m%d-%H%M%S-%3f";
+
+/// A way of persisting the cache document to disk.
+///
+/// Backends only move documents around, schema migrations happen on top of them.
+pub(crate) trait Backend {
+    /// Reads the raw cache document, which may have an outdated schema.
+    fn read(&self, filepath: &Path) -> Result<Document>;
+
+    /// Replaces the cache with a document, such that a crash leaves either the old or the new contents behind.
+    fn write(&self, filepath: &Path, document: &Document) -> Result<()>;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) enum StorageFormat {
+    Bson,
+    Json,
+    Sqlite,
+}
+
+impl StorageFormat {
+    /// Picks the format from the file extension, then from the contents of an existing file, falling back to the
+    /// configured format and then to BSON, such that the configured format only applies to new files.
+    pub(crate) fn from_filepath(filepath: &Path) -> Self {
+        let extension = filepath.extension().and_then(|extension| extension.to_str()).map(str::to_lowercase);
+        match extension.as_deref() {
+            Some("json") => Self::Json,
+            Some("sqlite" | "sqlite3" | "db") => Self::Sqlite,
+            Some("bson") => Self::Bson,
+            _ => Self::from_contents(filepath).unwrap_or_else(|| config().storage.unwrap_or(Self::Bson)),
+        }
+    }
+
+    /// Recognizes the format of an existing file from its first bytes.
+    fn from_contents(filepath: &Path) -> Option<Self> {
+        let file = File::open(filepath).ok()?;
+        let length = file.metadata().ok()?.len();
+        let mut header = Vec::new();
+        file.take(SQLITE_HEADER.len() as u64).read_to_end(&mut header).ok()?;
+
+        if header.starts_with(SQLITE_HEADER) {
+            Some(Self::Sqlite)
+        // BSON documents start with their length
+        } else if header.len() >= 4 && u32::from_le_bytes([header[0], header[1], header[2], header[3]]) as u64 == length {
+            Some(Self::Bson)
+        } else if header.iter().find(|byte| !byte.is_ascii_whitespace()) == Some(&b'{') {
+            Some(Self::Json)
+        } else {
+            None
+        }
+    }
+
+    pub(crate) fn backend(&self) -> Box<dyn Backend> {
+        match self {
+            StorageFormat::Bson => Box::new(BsonBackend),
+            StorageFormat::Json => Box::new(JsonBackend),
+            StorageFormat::Sqlite => Box::new(SqliteBackend),
+        }
+    }
+}
+
+impl std::fmt::Display for StorageFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StorageFormat::Bson => write!(f, "bson"),
+            StorageFormat::Json => write!(f, "json"),
+            StorageFormat::Sqlite => write!(f, "sqlite"),
+        }
+    }
+}
+
+/// Builds the path of a file stored next to the cache, e.g. `.wwida_cache.journal`.
+pub(crate) fn sibling_filepath(filepath: &Path, suffix: &str) -> PathBuf {
+    let mut filename = filepath.file_name().expect("wwida cache has no file name").to_os_string();
+    filename.push(suffix);
+    filepath.with_file_name(filename)
+}
+
+/// Replaces the contents of a file such that a crash leaves either the old or the new contents behind.
+pub(crate) fn write_atomic(filepath: &Path, bytes: &[u8]) -> Result<()> {
+    let temp_filepath = sibling_filepath(filepath, ".tmp");
+    let mut file = File::create(&temp_filepath)?;
+    file.write_all(bytes)?;
+    file.sync_all()?;
+    std::fs::rename(&temp_filepath, filepath)?;
+
+    // persist the rename itself, only possible on Unix-like systems
+    #[cfg(unix)]
+    if let Some(parent) = filepath.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+        File::open(parent)?.sync_all()?;
+    }
+    Ok(())
+}
+
+/// Reads a raw cache document, without interpreting it as a task list.
+pub(crate) fn read_document(filepath: &Path) -> Result<Document> {
+    StorageFormat::from_filepath(filepath).backend().read(filepath)
+}
+
+pub(crate) fn write_document(filepath: &Path, document: &Document) -> Result<()> {
+    StorageFormat::from_filepath(filepath).backend().write(filepath, document)
+}
+
+/// Advisory lock on a cache file, held for as long as the value lives.
+pub(crate) struct CacheLock(File);
+
+impl CacheLock {
+    /// Blocks until no other wwida process holds the lock on the cache.
+    pub(crate) fn acquire(filepath: &Path) -> Result<Self> {
+        let file = File::create(sibling_filepath(filepath, ".lock"))?;
+        file.lock_exclusive().with_context(|| "could not lock wwida cache")?;
+        Ok(Self(file))
+    }
+
+    /// Fails instead of blocking if another wwida process holds the lock on the cache.
+    pub(crate) fn try_acquire(filepath: &Path) -> Result<Self> {
+        let file = File::create(sibling_filepath(filepath, ".lock"))?;
+        file.try_lock_exclusive().with_context(|| "wwida cache is in use by another wwida process")?;
+        Ok(Self(file))
+    }
+}
+
+impl Drop for CacheLock {
+    fn drop(&mut self) {
+        let _ = self.0.unlock();
+    }
+}
+
+fn backup_directory(filepath: &Path) -> PathBuf {
+    sibling_filepath(filepath, ".backups")
+}
+
+/// Copies the cache into its backup directory, keeping only the latest [`BACKUP_COUNT`] backups.
+pub(crate) fn backup(filepath: &Path) -> Result<()> {
+    if !filepath.is_file() {
+        return Ok(());
+    }
+
+    let directory = backup_directory(filepath);
+    std::fs::create_dir_all(&directory)?;
+    // keep the extension, so that backups are read with the same backend as the cache
+    let mut filename = chrono::Local::now().format(BACKUP_TIMESTAMP_FORMAT).to_string();
+    if let Some(extension) = filepath.extension().and_then(|extension| extension.to_str()) {
+        filename = format!("{filename}.{extension}");
+    }
+    std::fs::copy(filepath, directory.join(filename))?;
+
+    for stale in list_backups(filepath)?.into_iter().skip(BACKUP_COUNT) {
+        std::fs::remove_file(stale)?;
+    }
+    Ok(())
+}
+
+/// Lists all backups of the cache, most recent first.
+pub(crate) fn list_backups(filepath: &Path) -> Result<Vec<PathBuf>> {
+    let directory = backup_directory(filepath);
+    if !directory.is_dir() {
+        return Ok(Vec::new());
+    }
+
+    let mut backups = std::fs::read_dir(directory)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<Vec<_>, _>>()?;
+    backups.sort_unstable_by(|a, b| b.cmp(a));
+    Ok(backups)
+}
+
+/// Parses the creation time of a backup back out of its file name.
+pub(crate) fn backup_timestamp(backup: &Path) -> Option<chrono::NaiveDateTime> {
+    let filename = backup.file_stem()?.to_str()?;
+    chrono::NaiveDateTime::parse_from_str(filename, BACKUP_TIMESTAMP_FORMAT).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backends_round_trip() {
+        let directory = std::env::temp_dir().join(format!("wwida-storage-{}", std::process::id()));
+        std::fs::create_dir_all(&directory).expect("could not create test directory");
+
+        let mut document = bson::doc! {
+            "tasks": [{ "id": 0_i64, "short": "round trip", "long": null }],
+            "archived": [],
+            "next_id": 1_i64,
+        };
+        schema::stamp(&mut document);
+
+        for filename in ["cache", "cache.json", "cache.sqlite"] {
+            let filepath = directory.join(filename);
+            write_document(&filepath, &document).expect("could not write");
+            let read = read_document(&filepath).expect("could not read");
+            assert_eq!(schema::version(&read).expect("no version"), schema::SCHEMA_VERSION, "{filename}");
+            assert_eq!(read.get_array("tasks").expect("no tasks"), document.get_array("tasks").unwrap(), "{filename}");
+            assert_eq!(read.get_i64("next_id").expect("no next ID"), 1, "{filename}");
+        }
+
+        std::fs::remove_dir_all(directory).expect("could not remove test directory");
+    }
+
+    #[test]
+    fn existing_files_keep_their_format() {
+        let directory = std::env::temp_dir().join(format!("wwida-formats-{}", std::process::id()));
+        std::fs::create_dir_all(&directory).expect("could not create test directory");
+        let mut document = bson::doc! { "tasks": [], "archived": [], "next_id": 0_i64 };
+        schema::stamp(&mut document);
+
+        // as after `wwida storage convert --format` into a file without extension, or changing the `storage` setting
+        let filepath = directory.join("cache");
+        assert_eq!(StorageFormat::from_filepath(&filepath), StorageFormat::Bson);
+        for format in [StorageFormat::Json, StorageFormat::Sqlite, StorageFormat::Bson] {
+            std::fs::remove_file(&filepath).ok();
+            format.backend().write(&filepath, &document).expect("could not write");
+            assert_eq!(StorageFormat::from_filepath(&filepath), format);
+            assert!(read_document(&filepath).is_ok(), "{format}");
+        }
+
+        std::fs::remove_dir_all(directory).expect("could not remove test directory");
+    }
+}
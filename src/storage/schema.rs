@@ -0,0 +1,179 @@
+use anyhow::Result;
+use bson::{Bson, Document};
+use uuid::Uuid;
+
+use crate::components::{
+    reminder::Reminder,
+    time::{moment::Moment, now, span::Span},
+};
+
+/// Version of the cache layout written by this version of wwida.
+pub(crate) const SCHEMA_VERSION: i32 = 9;
+
+const VERSION_KEY: &str = "version";
+
+type Migration = fn(&mut Document) -> Result<()>;
+
+/// Migrations between consecutive schema versions, the one at index `i` upgrades version `i` to `i + 1`.
+const MIGRATIONS: [Migration; SCHEMA_VERSION as usize] = [v0_to_v1, v1_to_v2, v2_to_v3, v3_to_v4, v4_to_v5, v5_to_v6, v6_to_v7, v7_to_v8, v8_to_v9];
+
+/// Reads the schema version of a cache document, unversioned caches are version 0.
+pub(crate) fn version(document: &Document) -> Result<i32> {
+    match document.get(VERSION_KEY) {
+        None => Ok(0),
+        Some(Bson::Int32(version)) => Ok(*version),
+        // text-based backends do not preserve integer widths
+        Some(Bson::Int64(version)) => i32::try_from(*version).map_err(|_| anyhow::anyhow!("invalid cache schema version {version}")),
+        Some(other) => Err(anyhow::anyhow!("invalid cache schema version {other}")),
+    }
+}
+
+/// Marks a freshly serialized cache document with the current schema version.
+pub(crate) fn stamp(document: &mut Document) {
+    document.insert(VERSION_KEY, SCHEMA_VERSION);
+}
+
+/// Upgrades a cache document of any older layout to the current one, one version at a time.
+pub(crate) fn migrate(document: &mut Document) -> Result<()> {
+    let version = version(document)?;
+    if version > SCHEMA_VERSION {
+        return Err(anyhow::anyhow!(
+            "cache has schema version {version}, but this wwida only understands up to {SCHEMA_VERSION}; please upgrade"
+        ));
+    } else if version < 0 {
+        return Err(anyhow::anyhow!("invalid cache schema version {version}"));
+    }
+
+    for migration in &MIGRATIONS[version as usize..] {
+        migration(document)?;
+    }
+    stamp(document);
+    Ok(())
+}
+
+/// Gets a mutable array field of a cache document, creating an empty one if missing.
+pub(crate) fn array_mut<'d>(document: &'d mut Document, key: &str) -> Result<&'d mut Vec<Bson>> {
+    if !document.contains_key(key) {
+        document.insert(key, Bson::Array(Vec::new()));
+    }
+    Ok(document.get_array_mut(key)?)
+}
+
+/// Applies a change to every active and archived task of a cache document.
+fn for_each_task(document: &mut Document, mut change: impl FnMut(&mut Document) -> Result<()>) -> Result<()> {
+    for key in ["tasks", "archived"] {
+        for (position, task) in array_mut(document, key)?.iter_mut().enumerate() {
+            change(task.as_document_mut().ok_or_else(|| anyhow::anyhow!("task {position} is not a document"))?)?;
+        }
+    }
+    Ok(())
+}
+
+/// Gives every task lacking a field the value it implicitly had before the field existed.
+fn insert_default(document: &mut Document, key: &str, value: Bson) -> Result<()> {
+    for_each_task(document, |task| {
+        if !task.contains_key(key) {
+            task.insert(key, value.clone());
+        }
+        Ok(())
+    })
+}
+
+/// Version 1 gives each task a persistent ID and UUID, and adds the archive.
+///
+/// Caches written before tasks carried their own ID referred to them by position, so the position becomes
+/// the persistent ID. This keeps `BlockedByTask` references pointing at the same tasks.
+fn v0_to_v1(document: &mut Document) -> Result<()> {
+    let positional = !document.contains_key("next_id");
+    let tasks = array_mut(document, "tasks")?;
+    let num_tasks = tasks.len();
+
+    for (position, task) in tasks.iter_mut().enumerate() {
+        let task = task.as_document_mut().ok_or_else(|| anyhow::anyhow!("task {position} is not a document"))?;
+        if positional {
+            task.insert("id", bson::to_bson(&position)?);
+        }
+        if !task.contains_key("uuid") {
+            task.insert("uuid", bson::to_bson(&Uuid::new_v4())?);
+        }
+    }
+
+    if positional {
+        document.insert("next_id", bson::to_bson(&num_tasks)?);
+    }
+    array_mut(document, "archived")?;
+    Ok(())
+}
+
+/// Version 2 lets tasks be subtasks of another task, existing tasks have no parent.
+fn v1_to_v2(document: &mut Document) -> Result<()> {
+    insert_default(document, "parent", Bson::Null)
+}
+
+/// Version 3 turns the single blocking task of `BlockedByTask` statuses into dependencies of the task.
+///
+/// Each such status now lists its blocking tasks, and tasks whose latest status is `BlockedByTask` depend on the
+/// task blocking them.
+fn v2_to_v3(document: &mut Document) -> Result<()> {
+    for_each_task(document, |task| {
+        let mut depends_on = Vec::new();
+        for status in array_mut(task, "statuses")?.iter_mut() {
+            depends_on.clear();
+            let blocked = status.as_document_mut().and_then(|status| status.get_document_mut("BlockedByTask").ok());
+            if let Some(blocked) = blocked {
+                if let Some(id) = blocked.remove("id") {
+                    blocked.insert("ids", vec![id.clone()]);
+                    depends_on.push(id);
+                }
+            }
+        }
+        if !task.contains_key("depends_on") {
+            task.insert("depends_on", depends_on);
+        }
+        Ok(())
+    })
+}
+
+/// Version 4 lets tasks recur, existing tasks happen once.
+fn v3_to_v4(document: &mut Document) -> Result<()> {
+    insert_default(document, "recurrence", Bson::Null)
+}
+
+/// Version 5 lets deadlines and status dates carry a time of day, stored as UTC instants.
+///
+/// Plain dates remain valid, so existing caches need no changes. The version still goes up, so that older versions
+/// of wwida refuse to read instants they would not understand.
+fn v4_to_v5(_document: &mut Document) -> Result<()> {
+    Ok(())
+}
+
+/// Version 6 adds reminders, existing tasks only get the one at their deadline.
+///
+/// Deadlines which have already passed count as notified, so that the first run of `wwida remind` does not fire
+/// for the whole backlog at once.
+fn v5_to_v6(document: &mut Document) -> Result<()> {
+    let now = now();
+    for_each_task(document, |task| {
+        if !task.contains_key("reminders") {
+            let deadline = task.get("deadline").cloned().map(bson::from_bson::<Option<Moment>>).transpose()?.flatten();
+            let reminder = Reminder { before: Span::default(), fired: deadline.is_some_and(|deadline| deadline.instant() <= now) };
+            task.insert("reminders", vec![bson::to_bson(&reminder)?]);
+        }
+        Ok(())
+    })
+}
+
+/// Version 7 lets tasks be snoozed until a wait date, existing tasks are not.
+fn v6_to_v7(document: &mut Document) -> Result<()> {
+    insert_default(document, "wait", Bson::Null)
+}
+
+/// Version 8 records the time spent on tasks, none for existing tasks.
+fn v7_to_v8(document: &mut Document) -> Result<()> {
+    insert_default(document, "time_log", Bson::Array(Vec::new()))
+}
+
+/// Version 9 records when tasks are added, which is unknown for existing tasks.
+fn v8_to_v9(document: &mut Document) -> Result<()> {
+    insert_default(document, "created", Bson::Null)
+}
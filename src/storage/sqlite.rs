@@ -0,0 +1,84 @@
+use std::path::Path;
+
+use anyhow::Result;
+use bson::{Bson, Document};
+use rusqlite::{params, Connection};
+
+use super::Backend;
+
+/// Top-level fields of the cache which hold tasks, stored one row per task.
+const TASK_LISTS: [&str; 2] = ["tasks", "archived"];
+
+const CREATE_TABLES: &str = "
+    CREATE TABLE IF NOT EXISTS meta (key TEXT PRIMARY KEY, value TEXT NOT NULL);
+    CREATE TABLE IF NOT EXISTS tasks (
+        list TEXT NOT NULL,
+        position INTEGER NOT NULL,
+        id INTEGER,
+        data TEXT NOT NULL,
+        PRIMARY KEY (list, position)
+    );
+";
+
+/// Embedded SQLite database, which copes better than a single document with long task histories.
+///
+/// Each task is stored as a JSON row, all other top-level fields of the cache go into the `meta` table.
+pub(crate) struct SqliteBackend;
+
+impl Backend for SqliteBackend {
+    fn read(&self, filepath: &Path) -> Result<Document> {
+        let connection = Connection::open(filepath)?;
+        connection.execute_batch(CREATE_TABLES)?;
+        let mut document = Document::new();
+
+        let mut statement = connection.prepare("SELECT key, value FROM meta")?;
+        let mut rows = statement.query([])?;
+        while let Some(row) = rows.next()? {
+            let value: serde_json::Value = serde_json::from_str(&row.get::<_, String>(1)?)?;
+            document.insert(row.get::<_, String>(0)?, bson::to_bson(&value)?);
+        }
+
+        let mut statement = connection.prepare("SELECT data FROM tasks WHERE list = ?1 ORDER BY position")?;
+        for list in TASK_LISTS {
+            let mut tasks = Vec::new();
+            let mut rows = statement.query([list])?;
+            while let Some(row) = rows.next()? {
+                let value: serde_json::Value = serde_json::from_str(&row.get::<_, String>(0)?)?;
+                tasks.push(bson::to_bson(&value)?);
+            }
+            document.insert(list, tasks);
+        }
+        Ok(document)
+    }
+
+    fn write(&self, filepath: &Path, document: &Document) -> Result<()> {
+        let mut connection = Connection::open(filepath)?;
+        connection.execute_batch(CREATE_TABLES)?;
+
+        // a single transaction, so that a crash leaves the previous contents in place
+        let transaction = connection.transaction()?;
+        transaction.execute("DELETE FROM meta", [])?;
+        transaction.execute("DELETE FROM tasks", [])?;
+        for (key, value) in document {
+            match value {
+                Bson::Array(tasks) if TASK_LISTS.contains(&key.as_str()) => {
+                    for (position, task) in tasks.iter().enumerate() {
+                        let id = task.as_document().and_then(|task| task.get("id")).and_then(Bson::as_i64);
+                        transaction.execute(
+                            "INSERT INTO tasks (list, position, id, data) VALUES (?1, ?2, ?3, ?4)",
+                            params![key, position, id, serde_json::to_string(task)?],
+                        )?;
+                    }
+                }
+                value => {
+                    transaction.execute(
+                        "INSERT INTO meta (key, value) VALUES (?1, ?2)",
+                        params![key, serde_json::to_string(value)?],
+                    )?;
+                }
+            }
+        }
+        transaction.commit()?;
+        Ok(())
+    }
+}